@@ -0,0 +1,106 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+use crate::variables::quote;
+
+/// `alias name=value ...` sets aliases, `alias name ...` shows them and
+/// `alias` alone lists them all, as the commands that set them.
+pub struct AliasCommand;
+
+impl Command for AliasCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let mut args = args.as_slice();
+        match args.first().map(String::as_str) {
+            Some("-p" | "--") => args = &args[1..],
+            Some(option) if option.starts_with('-') && option != "-" => {
+                writeln!(ctx.stderr, "alias: {}: invalid option", option)?;
+                writeln!(ctx.stderr, "alias: usage: alias [-p] [name[=value] ... ]")?;
+                return Ok(2);
+            }
+            _ => {}
+        }
+
+        if args.is_empty() {
+            for (name, value) in ctx.shell.aliases() {
+                writeln!(ctx.stdout, "alias {}={}", name, quote(value))?;
+            }
+            return Ok(0);
+        }
+
+        let mut status = 0;
+
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, value)) => {
+                    if !is_valid_alias_name(name) {
+                        writeln!(ctx.stderr, "alias: `{}': invalid alias name", name)?;
+                        status = 1;
+                        continue;
+                    }
+                    ctx.shell.aliases_mut().insert(name.to_string(), value.to_string());
+                }
+                None => match ctx.shell.aliases().get(arg) {
+                    Some(value) => writeln!(ctx.stdout, "alias {}={}", arg, quote(value))?,
+                    None => {
+                        writeln!(ctx.stderr, "alias: {}: not found", arg)?;
+                        status = 1;
+                    }
+                },
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-p"]
+    }
+}
+
+/// `unalias name ...` removes aliases, `unalias -a` all of them.
+pub struct UnaliasCommand;
+
+impl Command for UnaliasCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let mut args = args.as_slice();
+        match args.first().map(String::as_str) {
+            Some("-a") => {
+                ctx.shell.aliases_mut().clear();
+                return Ok(0);
+            }
+            Some("--") => args = &args[1..],
+            Some(option) if option.starts_with('-') && option != "-" => {
+                writeln!(ctx.stderr, "unalias: {}: invalid option", option)?;
+                writeln!(ctx.stderr, "unalias: usage: unalias [-a] name [name ...]")?;
+                return Ok(2);
+            }
+            _ => {}
+        }
+
+        if args.is_empty() {
+            writeln!(ctx.stderr, "unalias: usage: unalias [-a] name [name ...]")?;
+            return Ok(2);
+        }
+
+        let mut status = 0;
+
+        for name in args {
+            if ctx.shell.aliases_mut().remove(name).is_none() {
+                writeln!(ctx.stderr, "unalias: {}: not found", name)?;
+                status = 1;
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-a"]
+    }
+}
+
+/// Whether `name` can be an alias: a word the shell reads back as is, as
+/// the command name is when it is looked up.
+fn is_valid_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.chars().any(|c| c.is_whitespace() || "/$`=\\'\"|&;()<>".contains(c))
+}
@@ -0,0 +1,30 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+use crate::expand::evaluate_arithmetic;
+
+/// `let expression ...` evaluates arithmetic expressions, for their
+/// assignments: `let i++ 'n = n * 2'`. It succeeds when the last one is not
+/// 0, as `((expression))` does.
+pub struct LetCommand;
+
+impl Command for LetCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        if args.is_empty() {
+            writeln!(ctx.stderr, "let: expression expected")?;
+            return Ok(1);
+        }
+
+        let mut value = 0;
+        for expression in &args {
+            value = match evaluate_arithmetic(expression, ctx.shell) {
+                Ok(value) => value,
+                Err(err) => {
+                    writeln!(ctx.stderr, "let: {}", err)?;
+                    return Ok(err.status());
+                }
+            };
+        }
+
+        Ok(if value != 0 { 0 } else { 1 })
+    }
+}
@@ -0,0 +1,41 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+
+/// `bg [jobspec ...]`: continues stopped jobs, the current one by default,
+/// in the background.
+pub struct BgCommand;
+
+impl Command for BgCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        if !ctx.shell.job_control() {
+            return Err(ShellError::Other("bg: no job control".to_string()));
+        }
+
+        let specs = if args.is_empty() {
+            vec!["%+".to_string()]
+        } else {
+            args
+        };
+
+        let mut status = 0;
+
+        for spec in specs {
+            let id = match ctx.shell.jobs().find(&spec) {
+                Ok(id) => id,
+                Err(err) => {
+                    writeln!(ctx.stderr, "bg: {}", err)?;
+                    status = 1;
+                    continue;
+                }
+            };
+
+            ctx.shell.background(id)?;
+
+            if let Some(job) = ctx.shell.jobs().get(id) {
+                writeln!(ctx.stdout, "[{}]{} {} &", id, ctx.shell.jobs().mark(id), job.command)?;
+            }
+        }
+
+        Ok(status)
+    }
+}
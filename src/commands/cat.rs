@@ -1,55 +1,104 @@
-use std::io::stdin;
-use std::{env, fs};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
 
-use crate::commands::Command;
+use crate::commands::{Command, Context};
 use crate::error::*;
+use crate::signals;
 
 pub struct CatCommand;
 
 impl Command for CatCommand {
-    fn execute(&self, args: Vec<String>) -> Result<(), ShellError> {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let mut status = 0;
+
         if args.is_empty() {
             loop {
                 let mut input = String::new();
 
-                match stdin().read_line(&mut input) {
+                match ctx.read_line(&mut input) {
                     Ok(0) => {
-                        println!("CTRL + D exit...");
+                        if ctx.stdin_is_terminal {
+                            writeln!(ctx.stdout, "CTRL + D exit...")?;
+                        }
                         break;
                     }
                     Ok(_) => {
-                        print!("{}", &input);
+                        write!(ctx.stdout, "{}", &input)?;
+                        if ctx.stdout_is_terminal {
+                            ctx.stdout.flush()?;
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                        return Ok(signals::INTERRUPTED_STATUS);
                     }
                     Err(e) => {
-                        eprintln!("cat: error reading the input: {}", e);
+                        writeln!(ctx.stderr, "cat: error reading the input: {}", e)?;
                         continue;
                     }
                 };
             }
         } else {
-            let cur_dir = env::current_dir().map_err(|e| {
-                ShellError::Other(format!("cat: failed to get current directory: {}", e))
-            })?;
+            let cur_dir = ctx
+                .current_dir()
+                .map_err(|e| ShellError::Other(format!("cat: {}", e)))?;
 
             for arg in args {
                 let path = cur_dir.join(&arg);
                 if path.is_file() {
-                    let contents = match fs::read_to_string(path) {
-                        Ok(c) => c,
+                    let last_byte = match copy_file(ctx, &path) {
+                        Ok(last_byte) => last_byte,
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                            return Ok(signals::INTERRUPTED_STATUS);
+                        }
                         Err(e) => {
-                            eprintln!("cat: error reading file content '{}': {}", arg, e);
+                            writeln!(
+                                ctx.stderr,
+                                "cat: error reading file content '{}': {}",
+                                arg, e
+                            )?;
+                            status = 1;
                             continue;
                         }
                     };
 
-                    println!("{}%", contents);
+                    // Mark a missing final newline the way zsh does, but only
+                    // for humans: piped output must stay byte for byte.
+                    if last_byte.is_some_and(|b| b != b'\n') && ctx.stdout_is_terminal {
+                        writeln!(ctx.stdout, "%")?;
+                    }
                 } else if path.is_dir() {
-                    eprintln!("cat: src: Is a directory");
+                    writeln!(ctx.stderr, "cat: {}: Is a directory", arg)?;
+                    status = 1;
                 } else {
-                    eprintln!("cat: {}: No such file or directory", arg);
+                    writeln!(ctx.stderr, "cat: {}: No such file or directory", arg)?;
+                    status = 1;
                 }
             }
         }
-        Ok(())
+        Ok(status)
+    }
+}
+
+/// Writes a file to stdout a chunk at a time, stopping on Ctrl+C. Returns
+/// the last byte written, `None` for an empty file.
+fn copy_file(ctx: &mut Context, path: &Path) -> io::Result<Option<u8>> {
+    let mut file = File::open(path)?;
+    let mut buf = [0u8; 64 * 1024];
+    let mut last_byte = None;
+
+    loop {
+        if ctx.interrupted() {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+
+        let n = match file.read(&mut buf) {
+            Ok(0) => return Ok(last_byte),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        ctx.stdout.write_all(&buf[..n])?;
+        last_byte = Some(buf[n - 1]);
     }
 }
@@ -1,65 +1,60 @@
 use std::{env, path::Path};
 
-use crate::commands::Command;
+use crate::commands::{Command, Context};
 use crate::error::*;
 
 pub struct CdCommand;
 
 impl Command for CdCommand {
-    fn execute(&self, args: Vec<String>) -> Result<(), ShellError> {
-        match args.len() {
-            0 => change_to_home(),
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let result = match args.len() {
+            0 => change_to_home(ctx),
             1 => match args[0].as_str() {
-                "-" => change_to_previous(),
-                "~" => change_to_home(),
-                path if path.starts_with("~/") => match env::var("HOME") {
-                    Ok(home_dir) => {
+                "-" => change_to_previous(ctx),
+                "~" => change_to_home(ctx),
+                path if path.starts_with("~/") => match ctx.var("HOME") {
+                    Some(home_dir) => {
                         let expanded_path = path.replace("~", &home_dir);
-                        change_dir(&expanded_path)
+                        change_dir(ctx, &expanded_path)
                     }
-                    Err(_) => Err(ShellError::Other(
+                    None => Err(ShellError::Other(
                         "cd: HOME environment variable not set".to_string(),
                     )),
                 },
-                path => change_dir(path),
+                path => change_dir(ctx, path),
             },
             _ => Err(ShellError::Other("cd: too many arguments".to_string())),
-        }
+        };
+
+        result.map(|()| 0)
     }
 }
 
-fn change_to_home() -> Result<(), ShellError> {
-    match env::var("HOME") {
-        Ok(home_dir) => change_dir(&home_dir),
-        Err(_) => Err(ShellError::Other(
+fn change_to_home(ctx: &mut Context) -> Result<(), ShellError> {
+    match ctx.var("HOME") {
+        Some(home_dir) => change_dir(ctx, &home_dir),
+        None => Err(ShellError::Other(
             "cd: HOME environment variable not set".to_string(),
         )),
     }
 }
 
-fn change_to_previous() -> Result<(), ShellError> {
-    match env::var("OLDPWD") {
-        Ok(old_dir) => {
-            let current_dir = env::current_dir().map_err(|e| {
-                ShellError::Other(format!("cd: cannot get current directory: {}", e))
-            })?;
-
-            change_dir(&old_dir)?;
+fn change_to_previous(ctx: &mut Context) -> Result<(), ShellError> {
+    match ctx.var("OLDPWD") {
+        Some(old_dir) => {
+            change_dir(ctx, &old_dir)?;
 
-            unsafe { env::set_var("OLDPWD", current_dir) };
-
-            println!("{}", old_dir);
+            writeln!(ctx.stdout, "{}", old_dir)?;
 
             Ok(())
         }
-        Err(_) => Err(ShellError::Other("cd: OLDPWD not set".to_string())),
+        None => Err(ShellError::Other("cd: OLDPWD not set".to_string())),
     }
 }
 
-fn change_dir(path: &str) -> Result<(), ShellError> {
-    if let Ok(current_dir) = env::current_dir() {
-        unsafe { env::set_var("OLDPWD", current_dir) };
-    }
+/// Changes directory, keeping `PWD` and `OLDPWD` up to date.
+fn change_dir(ctx: &mut Context, path: &str) -> Result<(), ShellError> {
+    let previous_dir = env::current_dir().ok();
 
     let target_path = Path::new(path);
 
@@ -78,5 +73,13 @@ fn change_dir(path: &str) -> Result<(), ShellError> {
         return Err(ShellError::Other(err.to_string()));
     }
 
+    if let Some(previous_dir) = previous_dir {
+        ctx.shell
+            .set_var("OLDPWD", &previous_dir.to_string_lossy());
+    }
+    if let Ok(current_dir) = env::current_dir() {
+        ctx.shell.set_var("PWD", &current_dir.to_string_lossy());
+    }
+
     Ok(())
 }
@@ -0,0 +1,134 @@
+use crate::commands::{Command, Context};
+use crate::completion::Spec;
+use crate::error::*;
+use crate::variables::quote;
+
+const USAGE: &str =
+    "complete: usage: complete [-pr] [-fd] [-W wordlist] [-F function] [-C command] [name ...]";
+
+/// `complete [-fd] [-W wordlist] [-F function] [-C command] name ...` sets
+/// how Tab completes the arguments of the named commands; `complete -p`
+/// lists the specs and `complete -r` removes them. Functions and commands
+/// are run with the command name, the word being completed and the word
+/// before it, and print one candidate per line.
+pub struct CompleteCommand;
+
+impl Command for CompleteCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let Arguments {
+            spec,
+            print,
+            remove,
+            names,
+        } = match parse_args(args) {
+            Ok(arguments) => arguments,
+            Err(message) => {
+                writeln!(ctx.stderr, "complete: {}", message)?;
+                writeln!(ctx.stderr, "{}", USAGE)?;
+                return Ok(2);
+            }
+        };
+
+        if remove {
+            if names.is_empty() {
+                ctx.shell.completions_mut().clear();
+                return Ok(0);
+            }
+
+            let mut status = 0;
+            for name in &names {
+                if ctx.shell.completions_mut().remove(name).is_none() {
+                    status = no_spec(ctx, name)?;
+                }
+            }
+            return Ok(status);
+        }
+
+        if print || spec == Spec::default() {
+            if names.is_empty() {
+                for (name, spec) in ctx.shell.completions() {
+                    writeln!(ctx.stdout, "complete {} {}", spec, quote(name))?;
+                }
+                return Ok(0);
+            }
+
+            let mut status = 0;
+            for name in &names {
+                match ctx.shell.completion(name) {
+                    Some(spec) => writeln!(ctx.stdout, "complete {} {}", spec, quote(name))?,
+                    None => status = no_spec(ctx, name)?,
+                }
+            }
+            return Ok(status);
+        }
+
+        if names.is_empty() {
+            writeln!(ctx.stderr, "{}", USAGE)?;
+            return Ok(2);
+        }
+
+        for name in names {
+            ctx.shell.completions_mut().insert(name, spec.clone());
+        }
+        Ok(0)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-C", "-F", "-W", "-d", "-f", "-p", "-r"]
+    }
+}
+
+/// What the arguments of `complete` ask for.
+pub struct Arguments {
+    pub spec: Spec,
+    /// `-p`
+    pub print: bool,
+    /// `-r`
+    pub remove: bool,
+    pub names: Vec<String>,
+}
+
+/// Reads the arguments of `complete`, or says what is wrong with them.
+pub fn parse_args(args: Vec<String>) -> Result<Arguments, String> {
+    let mut arguments = Arguments {
+        spec: Spec::default(),
+        print: false,
+        remove: false,
+        names: Vec::new(),
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arguments.names.is_empty() || !arg.starts_with('-') || arg == "-" {
+            arguments.names.push(arg);
+            continue;
+        }
+
+        for option in arg.chars().skip(1) {
+            match option {
+                'f' => arguments.spec.files = true,
+                'd' => arguments.spec.directories = true,
+                'p' => arguments.print = true,
+                'r' => arguments.remove = true,
+                'W' | 'F' | 'C' => {
+                    let Some(value) = args.next() else {
+                        return Err(format!("-{}: option requires an argument", option));
+                    };
+                    match option {
+                        'W' => arguments.spec.words = Some(value),
+                        'F' => arguments.spec.function = Some(value),
+                        _ => arguments.spec.command = Some(value),
+                    }
+                }
+                _ => return Err(format!("-{}: invalid option", option)),
+            }
+        }
+    }
+
+    Ok(arguments)
+}
+
+fn no_spec(ctx: &mut Context, name: &str) -> Result<i32, ShellError> {
+    writeln!(ctx.stderr, "complete: {}: no completion specification", name)?;
+    Ok(1)
+}
@@ -0,0 +1,73 @@
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use crate::{error::ShellError, shell::Shell, signals};
+
+/// Everything a command needs to run: its standard streams and access to
+/// the shell that invoked it.
+///
+/// Commands must read and write through these streams instead of the
+/// process-wide `stdin()`/`stdout()`, so that the shell can point them at
+/// files, pipes or in-memory buffers.
+pub struct Context<'a> {
+    pub stdin: &'a mut dyn BufRead,
+    pub stdout: &'a mut dyn Write,
+    pub stderr: &'a mut dyn Write,
+    pub stdin_is_terminal: bool,
+    pub stdout_is_terminal: bool,
+    pub shell: &'a mut Shell,
+}
+
+impl Context<'_> {
+    pub fn var(&self, name: &str) -> Option<String> {
+        self.shell.var(name)
+    }
+
+    /// Whether Ctrl+C was pressed. Commands that may run for a long time
+    /// check it and stop with `signals::INTERRUPTED_STATUS`.
+    pub fn interrupted(&self) -> bool {
+        signals::interrupted()
+    }
+
+    /// Reads a line from stdin like `BufRead::read_line`, but gives up with
+    /// an `Interrupted` error when Ctrl+C is pressed instead of waiting for
+    /// the rest of the line.
+    pub fn read_line(&mut self, buf: &mut String) -> io::Result<usize> {
+        let mut line = Vec::new();
+
+        loop {
+            let available = match self.stdin.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted && !signals::interrupted() => {
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if available.is_empty() {
+                break;
+            }
+
+            let (used, done) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (available.len(), false),
+            };
+            line.extend_from_slice(&available[..used]);
+            self.stdin.consume(used);
+
+            if done {
+                break;
+            }
+        }
+
+        buf.push_str(&String::from_utf8_lossy(&line));
+        Ok(line.len())
+    }
+
+    pub fn current_dir(&self) -> Result<PathBuf, ShellError> {
+        env::current_dir()
+            .map_err(|e| ShellError::Other(format!("failed to get current directory: {}", e)))
+    }
+}
@@ -1,14 +1,26 @@
-use std::env;
-use std::fs::copy;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 
-use crate::commands::Command;
+use crate::commands::{Command, Context};
 use crate::error::*;
+use crate::signals;
 
 pub struct CpCommand;
 
 impl Command for CpCommand {
-    fn execute(&self, args: Vec<String>) -> Result<(), ShellError> {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        if args.is_empty() {
+            return Err(ShellError::ArgsNotFound("cp".to_string()));
+        }
+
+        if args.len() == 1 {
+            return Err(ShellError::Other(format!(
+                "cp: missing destination file operand after '{}'",
+                args[0]
+            )));
+        }
+
         let src = args[0..args.len() - 1].to_vec();
         let target = args
             .iter()
@@ -27,14 +39,18 @@ impl Command for CpCommand {
         }
 
         if !is_target_dir && one_file {
+            let cur_dir = ctx
+                .current_dir()
+                .map_err(|e| ShellError::Other(format!("cp: {}", e)))?;
+
             let src_path = if Path::new(&src[0]).is_relative() {
-                PathBuf::from(env::current_dir().unwrap()).join(&src[0])
+                cur_dir.join(&src[0])
             } else {
                 PathBuf::from(&src[0])
             };
 
             let target_path = if Path::new(&target).is_relative() {
-                PathBuf::from(env::current_dir().unwrap()).join(&target)
+                cur_dir.join(&target)
             } else {
                 PathBuf::from(&target)
             };
@@ -46,7 +62,10 @@ impl Command for CpCommand {
                 )));
             }
 
-            if let Err(e) = copy(&src[0], &target) {
+            if let Err(e) = copy(ctx, Path::new(&src[0]), Path::new(&target)) {
+                if e.kind() == io::ErrorKind::Interrupted {
+                    return Ok(signals::INTERRUPTED_STATUS);
+                }
                 return Err(ShellError::Other(format!(
                     "cp: cannot copy '{}' to '{}': {}",
                     &src[0], &target, e
@@ -92,7 +111,10 @@ impl Command for CpCommand {
                     }
                 }
 
-                if let Err(e) = copy(path, &dist) {
+                if let Err(e) = copy(ctx, path, Path::new(&dist)) {
+                    if e.kind() == io::ErrorKind::Interrupted {
+                        return Ok(signals::INTERRUPTED_STATUS);
+                    }
                     errors.push(format!("cp: cannot copy '{}' to '{}': {}", s, dist, e));
                 }
             }
@@ -102,6 +124,31 @@ impl Command for CpCommand {
             return Err(ShellError::Other(errors.join("\n")));
         }
 
-        Ok(())
+        Ok(0)
     }
 }
+
+/// Copies a file and its permissions like `fs::copy`, a chunk at a time so
+/// that Ctrl+C can stop a big copy.
+fn copy(ctx: &Context, from: &Path, to: &Path) -> io::Result<()> {
+    let mut source = File::open(from)?;
+    let permissions = source.metadata()?.permissions();
+    let mut dest = File::create(to)?;
+    let mut buf = vec![0u8; 1024 * 1024];
+
+    loop {
+        if ctx.interrupted() {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+
+        let n = match source.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        dest.write_all(&buf[..n])?;
+    }
+
+    fs::set_permissions(to, permissions)
+}
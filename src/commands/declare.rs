@@ -0,0 +1,59 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+
+const USAGE: &str = "declare: usage: declare -f [name ...] or declare -F [name ...]";
+
+/// `declare -f [name ...]` shows the definitions of functions, all of them
+/// without names; `declare -F` only their names.
+pub struct DeclareCommand;
+
+impl Command for DeclareCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let mut names_only = None;
+        let mut names = Vec::new();
+
+        for arg in args {
+            match arg.as_str() {
+                "-f" if names.is_empty() => names_only = Some(false),
+                "-F" if names.is_empty() => names_only = Some(true),
+                option if option.starts_with('-') && names.is_empty() => {
+                    writeln!(ctx.stderr, "declare: {}: invalid option", option)?;
+                    writeln!(ctx.stderr, "{}", USAGE)?;
+                    return Ok(2);
+                }
+                _ => names.push(arg),
+            }
+        }
+
+        let Some(names_only) = names_only else {
+            writeln!(ctx.stderr, "{}", USAGE)?;
+            return Ok(2);
+        };
+
+        if names.is_empty() {
+            names = ctx.shell.functions().keys().cloned().collect();
+        }
+
+        let mut status = 0;
+        for name in &names {
+            let Some(function) = ctx.shell.function(name).cloned() else {
+                // Like other shells, a missing function is only reported
+                // through the status.
+                status = 1;
+                continue;
+            };
+
+            if names_only {
+                writeln!(ctx.stdout, "declare -f {}", name)?;
+            } else {
+                writeln!(ctx.stdout, "{}", function)?;
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-F", "-f"]
+    }
+}
@@ -1,19 +1,19 @@
-use crate::commands::Command;
+use crate::commands::{Command, Context};
 use crate::error::*;
 
 pub struct EchoCommand;
 
 impl Command for EchoCommand {
-    fn execute(&self, args: Vec<String>) -> Result<(), ShellError> {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
         if args.is_empty() {
-            println!();
-            return Ok(());
+            writeln!(ctx.stdout)?;
+            return Ok(0);
         }
 
         let parsed_args = args.iter().map(|arg| process_escape(arg)).collect::<Vec<String>>().join(" ");
 
-        println!("{}", parsed_args);
-        Ok(())
+        writeln!(ctx.stdout, "{}", parsed_args)?;
+        Ok(0)
     }
 }
 
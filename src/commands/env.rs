@@ -0,0 +1,53 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+use crate::variables::{Variables, split_assignment};
+
+/// `env [-i] [-u name] [name=value ...] [command [arg ...]]`: prints the
+/// environment, or runs a program in a modified one.
+pub struct EnvCommand;
+
+impl Command for EnvCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let mut vars = ctx.shell.vars().clone();
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next_if(|arg| arg.starts_with('-')) {
+            match arg.as_str() {
+                "-i" | "-" => vars = Variables::default(),
+                "-u" => match args.next() {
+                    Some(name) => vars.unset(&name),
+                    None => {
+                        writeln!(ctx.stderr, "env: option requires an argument -- 'u'")?;
+                        return Ok(125);
+                    }
+                },
+                "--" => break,
+                _ => {
+                    writeln!(ctx.stderr, "env: invalid option -- '{}'", &arg[1..])?;
+                    return Ok(125);
+                }
+            }
+        }
+
+        while let Some(arg) = args.next_if(|arg| split_assignment(arg).is_some()) {
+            if let Some((name, value)) = split_assignment(&arg) {
+                vars.export(name, Some(value));
+            }
+        }
+
+        let argv: Vec<String> = args.collect();
+        if argv.is_empty() {
+            for (name, value) in vars.exported() {
+                writeln!(ctx.stdout, "{}={}", name, value)?;
+            }
+            return Ok(0);
+        }
+
+        ctx.stdout.flush()?;
+        ctx.shell.run_external(&argv, &vars)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-i", "-u"]
+    }
+}
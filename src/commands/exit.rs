@@ -1,10 +1,27 @@
-use crate::commands::Command;
+use crate::commands::{Command, Context};
 use crate::error::ShellError;
 
 pub struct ExitCommand;
 
 impl Command for ExitCommand {
-    fn execute(&self, _args: Vec<String>) -> Result<(), ShellError> {
-        std::process::exit(0);
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let status = match args.first() {
+            None => ctx.shell.last_status(),
+            Some(arg) => match arg.parse::<i64>() {
+                // Like other shells, only the low 8 bits reach the parent.
+                Ok(n) => (n & 0xff) as i32,
+                Err(_) => {
+                    writeln!(ctx.stderr, "exit: {}: numeric argument required", arg)?;
+                    2
+                }
+            },
+        };
+
+        if args.len() > 1 {
+            return Err(ShellError::Other("exit: too many arguments".to_string()));
+        }
+
+        ctx.shell.request_exit(status);
+        Ok(status)
     }
 }
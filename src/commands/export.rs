@@ -0,0 +1,63 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+use crate::variables::{is_valid_name, quote};
+
+pub struct ExportCommand;
+
+impl Command for ExportCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let mut unexport = false;
+        let mut names = Vec::new();
+
+        for arg in args {
+            match arg.as_str() {
+                "-n" if names.is_empty() => unexport = true,
+                "-p" if names.is_empty() => {}
+                option if option.starts_with('-') && names.is_empty() => {
+                    writeln!(ctx.stderr, "export: {}: invalid option", option)?;
+                    writeln!(ctx.stderr, "export: usage: export [-n] [name[=value] ...] or export -p")?;
+                    return Ok(2);
+                }
+                _ => names.push(arg),
+            }
+        }
+
+        if names.is_empty() {
+            for (name, value) in ctx.shell.vars().exported() {
+                writeln!(ctx.stdout, "export {}={}", name, quote(value))?;
+            }
+            return Ok(0);
+        }
+
+        let mut status = 0;
+
+        for arg in names {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+
+            if !is_valid_name(name) {
+                writeln!(ctx.stderr, "export: `{}': not a valid identifier", arg)?;
+                status = 1;
+                continue;
+            }
+
+            let vars = ctx.shell.vars_mut();
+            if unexport {
+                if let Some(value) = value {
+                    vars.set(name, value);
+                }
+                vars.unexport(name);
+            } else {
+                vars.export(name, value);
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-n", "-p"]
+    }
+}
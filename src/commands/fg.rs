@@ -0,0 +1,28 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+
+/// `fg [jobspec]`: brings a job, the current one by default, to the
+/// foreground and waits for it.
+pub struct FgCommand;
+
+impl Command for FgCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        if !ctx.shell.job_control() {
+            return Err(ShellError::Other("fg: no job control".to_string()));
+        }
+
+        let spec = args.first().map_or("%+", String::as_str);
+        let id = ctx
+            .shell
+            .jobs()
+            .find(spec)
+            .map_err(|err| ShellError::Other(format!("fg: {}", err)))?;
+
+        if let Some(job) = ctx.shell.jobs().get(id) {
+            writeln!(ctx.stdout, "{}", job.command)?;
+        }
+        ctx.stdout.flush()?;
+
+        ctx.shell.foreground(id)
+    }
+}
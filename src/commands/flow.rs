@@ -0,0 +1,95 @@
+use crate::commands::{Command, Context};
+use crate::error::ShellError;
+use crate::shell::Flow;
+
+/// `break [n]` leaves the `n` innermost loops, 1 by default.
+pub struct BreakCommand;
+
+/// `continue [n]` goes on with the next iteration of the `n`th innermost
+/// loop, 1 by default.
+pub struct ContinueCommand;
+
+/// `return [n]` ends the running function with status `n`, by default that
+/// of the last command.
+pub struct ReturnCommand;
+
+impl Command for BreakCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        leave_loops(ctx, "break", &args, Flow::Break)
+    }
+}
+
+impl Command for ContinueCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        leave_loops(ctx, "continue", &args, Flow::Continue)
+    }
+}
+
+impl Command for ReturnCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        if !ctx.shell.in_function() {
+            writeln!(ctx.stderr, "return: can only `return' from a function")?;
+            return Ok(1);
+        }
+        if args.len() > 1 {
+            writeln!(ctx.stderr, "return: too many arguments")?;
+            return Ok(1);
+        }
+
+        let status = match args.first() {
+            None => ctx.shell.last_status(),
+            Some(arg) => match arg.parse::<i64>() {
+                // Only the low 8 bits, as with `exit`.
+                Ok(n) => (n & 0xff) as i32,
+                Err(_) => {
+                    writeln!(ctx.stderr, "return: {}: numeric argument required", arg)?;
+                    2
+                }
+            },
+        };
+
+        ctx.shell.set_flow(Flow::Return(status));
+        Ok(status)
+    }
+}
+
+fn leave_loops(
+    ctx: &mut Context,
+    name: &str,
+    args: &[String],
+    flow: fn(usize) -> Flow,
+) -> Result<i32, ShellError> {
+    if args.len() > 1 {
+        writeln!(ctx.stderr, "{}: too many arguments", name)?;
+        return Ok(1);
+    }
+
+    let count = match args.first() {
+        None => 1,
+        Some(arg) => match arg.parse::<i64>() {
+            Ok(n) if n >= 1 => n as usize,
+            Ok(_) => {
+                writeln!(ctx.stderr, "{}: {}: loop count out of range", name, arg)?;
+                return Ok(1);
+            }
+            Err(_) => {
+                writeln!(ctx.stderr, "{}: {}: numeric argument required", name, arg)?;
+                return Ok(1);
+            }
+        },
+    };
+
+    let depth = ctx.shell.loop_depth();
+    if depth == 0 {
+        writeln!(
+            ctx.stderr,
+            "{}: only meaningful in a `for', `while', or `until' loop",
+            name
+        )?;
+        return Ok(0);
+    }
+
+    // Asking for more loops than there are leaves all of them.
+    ctx.shell.set_flow(flow(count.min(depth)));
+    Ok(0)
+}
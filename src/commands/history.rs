@@ -0,0 +1,63 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+
+/// `history [n]`, `history -c`, `history -d offset`: lists the last n
+/// entries or all of them, clears the list, or deletes one entry, a
+/// negative offset counting back from the end.
+pub struct HistoryCommand;
+
+impl Command for HistoryCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        match args.first().map(String::as_str) {
+            Some("-c") => {
+                ctx.shell.history_mut().clear();
+                Ok(0)
+            }
+            Some("-d") => {
+                let Some(offset) = args.get(1) else {
+                    writeln!(ctx.stderr, "history: -d: option requires an argument")?;
+                    return Ok(2);
+                };
+
+                let len = ctx.shell.history().entries().len() as i64;
+                let number = match offset.parse::<i64>() {
+                    Ok(offset) if offset < 0 => len + offset + 1,
+                    Ok(offset) => offset,
+                    Err(_) => 0,
+                };
+
+                if number < 1 || ctx.shell.history_mut().remove(number as usize).is_none() {
+                    writeln!(ctx.stderr, "history: {}: history position out of range", offset)?;
+                    return Ok(1);
+                }
+                Ok(0)
+            }
+            Some(option) if option.starts_with('-') => {
+                writeln!(ctx.stderr, "history: {}: invalid option", option)?;
+                writeln!(ctx.stderr, "history: usage: history [-c] [-d offset] [n]")?;
+                Ok(2)
+            }
+            count => {
+                let entries = ctx.shell.history().entries();
+                let count = match count.map(str::parse::<usize>) {
+                    None => entries.len(),
+                    Some(Ok(count)) => count.min(entries.len()),
+                    Some(Err(_)) => {
+                        writeln!(ctx.stderr, "history: {}: numeric argument required", args[0])?;
+                        return Ok(1);
+                    }
+                };
+
+                let start = entries.len() - count;
+                for (index, entry) in entries.iter().enumerate().skip(start) {
+                    writeln!(ctx.stdout, "{:5}  {}", index + 1, entry)?;
+                }
+                Ok(0)
+            }
+        }
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-c", "-d"]
+    }
+}
@@ -0,0 +1,77 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+use crate::jobs::JobState;
+
+/// `jobs [-l | -p] [jobspec ...]`: lists the jobs, with their pids for
+/// `-l`, or only their process ids for `-p`.
+pub struct JobsCommand;
+
+impl Command for JobsCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let mut long = false;
+        let mut pids_only = false;
+        let mut specs = Vec::new();
+
+        for arg in args {
+            match arg.as_str() {
+                "-l" => long = true,
+                "-p" => pids_only = true,
+                option if option.starts_with('-') => {
+                    writeln!(ctx.stderr, "jobs: {}: invalid option", option)?;
+                    writeln!(ctx.stderr, "jobs: usage: jobs [-lp] [jobspec ...]")?;
+                    return Ok(2);
+                }
+                _ => specs.push(arg),
+            }
+        }
+
+        ctx.shell.jobs_mut().reap();
+
+        let mut status = 0;
+        let ids: Vec<usize> = if specs.is_empty() {
+            ctx.shell.jobs().iter().map(|job| job.id).collect()
+        } else {
+            let mut ids = Vec::new();
+            for spec in &specs {
+                match ctx.shell.jobs().find(spec) {
+                    Ok(id) => ids.push(id),
+                    Err(err) => {
+                        writeln!(ctx.stderr, "jobs: {}", err)?;
+                        status = 1;
+                    }
+                }
+            }
+            ids
+        };
+
+        for id in ids {
+            let jobs = ctx.shell.jobs();
+            let Some(job) = jobs.get(id) else {
+                continue;
+            };
+
+            if pids_only {
+                writeln!(ctx.stdout, "{}", job.processes[0].pid)?;
+            } else if long {
+                let line = jobs.format(job);
+                let (number, rest) = line.split_at(line.find("  ").unwrap_or(0));
+                writeln!(ctx.stdout, "{} {}{}", number, job.processes[0].pid, rest)?;
+            } else {
+                writeln!(ctx.stdout, "{}", jobs.format(job))?;
+            }
+
+            // Listing a finished job is its notification.
+            if job.state() == JobState::Done {
+                ctx.shell.jobs_mut().remove(id);
+            } else if let Some(job) = ctx.shell.jobs_mut().get_mut(id) {
+                job.notified = true;
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-l", "-p"]
+    }
+}
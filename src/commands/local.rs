@@ -0,0 +1,41 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+use crate::variables::is_valid_name;
+
+/// `local name[=value] ...` makes variables local to the running function:
+/// they get their old value back when it returns. A name without a value
+/// starts out unset.
+pub struct LocalCommand;
+
+impl Command for LocalCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        if !ctx.shell.in_function() {
+            writeln!(ctx.stderr, "local: can only be used in a function")?;
+            return Ok(1);
+        }
+
+        let mut status = 0;
+
+        for arg in args {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+
+            if !is_valid_name(name) {
+                writeln!(ctx.stderr, "local: `{}': not a valid identifier", arg)?;
+                status = 1;
+                continue;
+            }
+
+            let vars = ctx.shell.vars_mut();
+            vars.make_local(name);
+            match value {
+                Some(value) => vars.set(name, value),
+                None => vars.unset(name),
+            }
+        }
+
+        Ok(status)
+    }
+}
@@ -23,7 +23,7 @@ pub fn get_detailed_file_info(
 ) -> Result<Vec<String>, ShellError> {
     let metadata = path.symlink_metadata()?;
 
-    let permission = get_permissions(&metadata, &path);
+    let permission = get_permissions(&metadata, path);
 
     let size = if metadata.file_type().is_char_device() || metadata.file_type().is_block_device() {
         let (major, minor) = get_major_minor(&metadata);
@@ -1,10 +1,10 @@
 use std::{
     fs::Metadata,
     os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt},
-    path::PathBuf,
+    path::Path,
 };
 
-pub fn get_permissions(metadata: &Metadata, path: &PathBuf) -> String {
+pub fn get_permissions(metadata: &Metadata, path: &Path) -> String {
     let mode = metadata.permissions().mode();
     let mut permissions = String::new();
 
@@ -68,7 +68,7 @@ fn get_file_type(metadata: &Metadata) -> char {
     }
 }
 
-fn has_acl(path: &PathBuf) -> bool {
+fn has_acl(path: &Path) -> bool {
     use std::ffi::CString;
     use std::os::raw::c_char;
 
@@ -15,7 +15,7 @@ use crate::{
 };
 
 pub fn add_dot_entries(
-    dir: &PathBuf,
+    dir: &Path,
     result: &mut Vec<Vec<String>>,
     total_blocks: &mut u64,
     max_len: &mut usize,
@@ -49,7 +49,7 @@ pub fn add_dot_entries(
 
 pub fn format_detailed_file_info(
     max_lens: &HashMap<usize, usize>,
-    path: &Vec<String>,
+    path: &[String],
     max_size_len: &usize,
 ) -> String {
     let mut result = String::new();
@@ -65,7 +65,7 @@ pub fn format_detailed_file_info(
             if info.contains(",") {
                 let parts: Vec<&str> = info.split(',').collect();
                 let spaces_to_add = max_size_len - info.len();
-                let spaces: String = std::iter::repeat(' ').take(spaces_to_add).collect();
+                let spaces = " ".repeat(spaces_to_add);
                 let formatted = format!("{}, {}{}", parts[0].trim(), spaces, parts[1].trim());
                 result.push_str(&format!("{:>width$} ", formatted, width = max_width));
             } else {
@@ -79,7 +79,7 @@ pub fn format_detailed_file_info(
     result
 }
 
-pub fn format_path(path: &PathBuf, file_name: &mut String, flags: &Flag) -> Result<(), ShellError> {
+pub fn format_path(path: &Path, file_name: &mut String, flags: &Flag) -> Result<(), ShellError> {
     let metadata = path.symlink_metadata()?;
     let mode = metadata.permissions().mode();
 
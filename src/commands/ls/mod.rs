@@ -2,12 +2,13 @@ use std::path::PathBuf;
 
 use crate::{
     commands::{
-        Command,
-        ls::{output::LsOutput, processor::LsProcessor},
+        Command, Context,
+        ls::processor::LsProcessor,
     },
     error::ShellError,
 };
 
+pub use crate::commands::ls::output::LsOutput;
 pub use crate::commands::ls::parser::Flag;
 
 mod file_info;
@@ -28,30 +29,43 @@ pub struct Directory {
 pub struct LsCommand;
 
 impl Command for LsCommand {
-    fn execute(&self, args: Vec<String>) -> Result<(), ShellError> {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
         let mut directories: Vec<PathBuf> = Vec::new();
         let mut files: Vec<PathBuf> = Vec::new();
         let mut file_result: Vec<Vec<String>> = Vec::new();
         let mut dir_results: Vec<Directory> = Vec::new();
 
-        let flags = Flag::parse(&args, &mut directories, &mut files)?;
+        // Like ls(1), bad options and missing operands are "serious trouble".
+        let flags = match Flag::parse(&args, &mut directories, &mut files) {
+            Ok(flags) => flags,
+            Err(err) => {
+                writeln!(ctx.stderr, "ls: {}", err)?;
+                return Ok(2);
+            }
+        };
 
         if directories.is_empty() && files.is_empty() {
             directories.push(PathBuf::from("."));
         }
         let mut max_files_len = 0;
         LsProcessor::process_files(&files, &flags, &mut max_files_len, &mut file_result)?;
-        LsProcessor::process_directories(&directories, &flags, &mut dir_results)?;
+        LsProcessor::process_directories(&directories, &flags, &mut dir_results, ctx.stderr)?;
 
         LsOutput::print_results(
+            ctx.stdout,
+            ctx.stdout_is_terminal,
             &file_result,
             &dir_results,
             &directories.len(),
             &files.len(),
             &max_files_len,
             &flags,
-        );
+        )?;
 
-        Ok(())
+        Ok(0)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-a", "-F", "-l"]
     }
 }
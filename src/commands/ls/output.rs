@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
 
 use terminal_size::{Width, terminal_size};
 
@@ -9,42 +12,55 @@ use super::{Directory, formatter::format_detailed_file_info, parser::Flag};
 pub struct LsOutput;
 
 impl LsOutput {
+    #[allow(clippy::too_many_arguments)]
     pub fn print_results(
-        file_result: &Vec<Vec<String>>,
-        dir_results: &Vec<Directory>,
+        out: &mut dyn Write,
+        is_terminal: bool,
+        file_result: &[Vec<String>],
+        dir_results: &[Directory],
         directories_length: &usize,
         files_length: &usize,
         max_files_len: &usize,
         flags: &Flag,
-    ) {
+    ) -> io::Result<()> {
         // Print files
         if !file_result.is_empty() {
-            let mut file_result_clone = file_result.clone();
-            Self::print(&mut file_result_clone, max_files_len, flags);
+            let mut file_result_clone = file_result.to_vec();
+            Self::print(
+                out,
+                is_terminal,
+                &mut file_result_clone,
+                max_files_len,
+                flags,
+            )?;
             if !dir_results.is_empty() {
-                println!();
+                writeln!(out)?;
             }
         }
 
         // Print directories
         for (i, dir) in dir_results.iter().enumerate() {
             if directories_length + files_length > 1 {
-                println!("{}:", dir.path.display());
+                writeln!(out, "{}:", dir.path.display())?;
             }
 
             if flags.l {
-                println!("total {}:", dir.total_blocks);
+                writeln!(out, "total {}:", dir.total_blocks)?;
             }
 
             let mut entries_clone = dir.entries.clone();
-            Self::print(&mut entries_clone, &dir.max_len, flags);
+            Self::print(out, is_terminal, &mut entries_clone, &dir.max_len, flags)?;
             if i < directories_length - 1 {
-                println!();
+                writeln!(out)?;
             }
         }
+
+        Ok(())
     }
 
-    fn format_result(result: &Vec<Vec<String>>, term_width: usize) -> String {
+    /// Lays out the names in `result` in columns filling `term_width`, as
+    /// `ls` does on a terminal.
+    pub fn format_result(result: &[Vec<String>], term_width: usize) -> String {
         if result.is_empty() {
             return String::new();
         }
@@ -127,7 +143,13 @@ impl LsOutput {
         result
     }
 
-    fn print(result: &mut Vec<Vec<String>>, max_size_len: &usize, flags: &Flag) {
+    fn print(
+        out: &mut dyn Write,
+        is_terminal: bool,
+        result: &mut [Vec<String>],
+        max_size_len: &usize,
+        flags: &Flag,
+    ) -> io::Result<()> {
         let mut max_lens: HashMap<usize, usize> = HashMap::new();
 
         if flags.l {
@@ -141,10 +163,20 @@ impl LsOutput {
                 }
             }
             for path in result.iter() {
-                println!(
+                writeln!(
+                    out,
                     "{}",
                     format_detailed_file_info(&max_lens, path, max_size_len)
-                );
+                )?;
+            }
+        } else if !is_terminal {
+            // Like ls(1), list one entry per line when the output is piped.
+            for entry in result.iter() {
+                writeln!(
+                    out,
+                    "{}",
+                    entry.first().map(String::as_str).unwrap_or_default()
+                )?;
             }
         } else {
             let term_width = if let Some((Width(w), _)) = terminal_size() {
@@ -155,7 +187,9 @@ impl LsOutput {
 
             let res = Self::format_result(result, term_width);
 
-            print!("{res}");
+            write!(out, "{res}")?;
         }
+
+        Ok(())
     }
 }
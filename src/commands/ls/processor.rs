@@ -1,4 +1,4 @@
-use std::{fs::read_dir, path::PathBuf};
+use std::{fs::read_dir, io::Write, path::PathBuf};
 
 use super::{
     Directory,
@@ -40,6 +40,7 @@ impl LsProcessor {
         directories: &[PathBuf],
         flags: &Flag,
         dir_results: &mut Vec<Directory>,
+        errors: &mut dyn Write,
     ) -> Result<(), ShellError> {
         for dir in directories {
             let entries = read_dir(dir).map_err(|e| {
@@ -71,6 +72,7 @@ impl LsProcessor {
                 &mut dir_entry_result,
                 &mut total_blocks,
                 &mut max_len,
+                errors,
             )?;
 
             dir_results.push(Directory {
@@ -97,14 +99,15 @@ impl LsProcessor {
         dir_entry_result: &mut Vec<Vec<String>>,
         total_blocks: &mut u64,
         max_len: &mut usize,
+        errors: &mut dyn Write,
     ) -> Result<(), ShellError> {
         let mut paths: Vec<_> = entries
             .filter_map(|entry| entry.ok())
             .filter(|entry| {
-                if !flags.a {
-                    if let Some(name) = entry.file_name().to_str() {
-                        return !name.starts_with('.');
-                    }
+                if !flags.a
+                    && let Some(name) = entry.file_name().to_str()
+                {
+                    return !name.starts_with('.');
                 }
                 true
             })
@@ -122,7 +125,7 @@ impl LsProcessor {
                 match get_detailed_file_info(&path, Some(total_blocks), max_len, flags) {
                     Ok(info) => dir_entry_result.push(info),
                     Err(e) => {
-                        eprintln!("{}", e);
+                        writeln!(errors, "{}", e)?;
                         continue;
                     }
                 }
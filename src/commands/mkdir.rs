@@ -1,11 +1,11 @@
-use crate::commands::Command;
+use crate::commands::{Command, Context};
 use crate::error::*;
 use std::fs;
 
 pub struct MkdirCommand;
 
 impl Command for MkdirCommand {
-    fn execute(&self, args: Vec<String>) -> Result<(), ShellError> {
+    fn execute(&self, _ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
         if args.is_empty() {
             return Err(ShellError::Other("mkdir: missing operand".to_owned()));
         }
@@ -22,6 +22,6 @@ impl Command for MkdirCommand {
             return Err(ShellError::Other(errors.join("\n")));
         }
 
-        Ok(())
+        Ok(0)
     }
 }
@@ -1,9 +1,19 @@
 use crate::error::ShellError;
 
+pub use context::Context;
+
 pub trait Command {
-    fn execute(&self, args: Vec<String>) -> Result<(), ShellError>;
+    /// Runs the command and returns its exit status. Failures may also be
+    /// returned as an error, which the shell reports with `err.status()`.
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError>;
+
+    /// The options the command accepts, offered by Tab completion.
+    fn flags(&self) -> &'static [&'static str] {
+        &[]
+    }
 }
 
+pub mod context;
 pub mod pwd;
 pub mod cd;
 pub mod ls;
@@ -14,6 +24,22 @@ pub mod mv;
 pub mod rm;
 pub mod echo;
 pub mod exit;
+pub mod export;
+pub mod unset;
+pub mod env;
+pub mod set;
+pub mod jobs;
+pub mod fg;
+pub mod bg;
+pub mod wait;
+pub mod history;
+pub mod complete;
+pub mod source;
+pub mod flow;
+pub mod local;
+pub mod declare;
+pub mod alias;
+pub mod arithmetic;
 
 pub use exit::ExitCommand;
 pub use pwd::PwdCommand;
@@ -24,4 +50,20 @@ pub use ls::LsCommand;
 pub use rm::RmCommand;
 pub use mv::MvCommand;
 pub use cp::CpCommand;
-pub use cat::CatCommand;
\ No newline at end of file
+pub use cat::CatCommand;
+pub use export::ExportCommand;
+pub use unset::UnsetCommand;
+pub use env::EnvCommand;
+pub use set::SetCommand;
+pub use jobs::JobsCommand;
+pub use fg::FgCommand;
+pub use bg::BgCommand;
+pub use wait::WaitCommand;
+pub use history::HistoryCommand;
+pub use complete::CompleteCommand;
+pub use source::SourceCommand;
+pub use flow::{BreakCommand, ContinueCommand, ReturnCommand};
+pub use local::LocalCommand;
+pub use declare::DeclareCommand;
+pub use alias::{AliasCommand, UnaliasCommand};
+pub use arithmetic::LetCommand;
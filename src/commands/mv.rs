@@ -1,13 +1,13 @@
 use std::fs;
 use std::path::Path;
 
-use crate::commands::Command;
+use crate::commands::{Command, Context};
 use crate::error::ShellError;
 
 pub struct MvCommand;
 
 impl Command for MvCommand {
-    fn execute(&self, args: Vec<String>) -> Result<(), ShellError> {
+    fn execute(&self, _ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
         if args.is_empty() {
             return Err(ShellError::ArgsNotFound("mv".to_string()));
         }
@@ -24,10 +24,12 @@ impl Command for MvCommand {
         let source_path = Path::new(&sources[0]);
 
         if sources.len() > 1 {
-            move_multiple_sources(sources, dest_path)
+            move_multiple_sources(sources, dest_path)?;
         } else {
-            move_single_source(source_path, dest_path)
+            move_single_source(source_path, dest_path)?;
         }
+
+        Ok(0)
     }
 }
 
@@ -1,16 +1,14 @@
-use std::env;
-
-use crate::commands::Command;
+use crate::commands::{Command, Context};
 use crate::error::*;
 
 pub struct PwdCommand;
 
 impl Command for PwdCommand {
-    fn execute(&self, _args: Vec<String>) -> Result<(), ShellError> {
-        match env::current_dir() {
+    fn execute(&self, ctx: &mut Context, _args: Vec<String>) -> Result<i32, ShellError> {
+        match ctx.current_dir() {
             Ok(path) => {
-                println!("{}", path.display());
-                Ok(())
+                writeln!(ctx.stdout, "{}", path.display())?;
+                Ok(0)
             },
             Err(err) => Err(ShellError::Other(format!("error happened: {}", err)))
         }
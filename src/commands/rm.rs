@@ -1,12 +1,12 @@
-use std::{env, fs};
+use std::fs;
 
-use crate::commands::Command;
+use crate::commands::{Command, Context};
 use crate::error::*;
 
 pub struct RmCommand;
 
 impl Command for RmCommand {
-    fn execute(&self, args: Vec<String>) -> Result<(), ShellError> {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
         if args.is_empty() {
             return Err(ShellError::Other("missing operand".into()));
         }
@@ -33,51 +33,71 @@ impl Command for RmCommand {
             return Err(ShellError::Other("missing operand".into()));
         }
 
-        let cur_dir = env::current_dir().map_err(|e| {
-            ShellError::Other(format!("rm: failed to get current directory: {}", e))
-        })?;
+        let cur_dir = ctx
+            .current_dir()
+            .map_err(|e| ShellError::Other(format!("rm: {}", e)))?;
+
+        let mut status = 0;
 
         for elem in targets {
             if elem.chars().all(|c| c == '.' || c == '/') {
-                eprintln!(
+                writeln!(
+                    ctx.stderr,
                     "rm: refusing to remove '.' or '..' directory: skipping '{}'",
                     elem
-                );
+                )?;
+                status = 1;
                 continue;
             }
 
             let path = cur_dir.join(&elem);
             if !path.exists() {
-                eprintln!("rm: cannot remove '{}': No such file or directory", elem);
+                writeln!(
+                    ctx.stderr,
+                    "rm: cannot remove '{}': No such file or directory",
+                    elem
+                )?;
+                status = 1;
                 continue;
             }
 
             if path.is_file() {
                 if let Err(e) = fs::remove_file(&path) {
-                    eprintln!("rm: failed to remove file '{}': {}", elem, e);
+                    writeln!(ctx.stderr, "rm: failed to remove file '{}': {}", elem, e)?;
+                    status = 1;
                 }
             } else if path.is_dir() {
                 if recursive {
                     if let Err(e) = fs::remove_dir_all(&path) {
-                        eprintln!(
+                        writeln!(
+                            ctx.stderr,
                             "rm: failed to remove directory recursively '{}': {}",
                             elem, e
-                        );
+                        )?;
+                        status = 1;
                     }
                 } else {
-                    eprintln!(
+                    writeln!(
+                        ctx.stderr,
                         "rm: cannot remove '{}': Is a directory. Use -r to remove recursively.",
                         elem
-                    );
+                    )?;
+                    status = 1;
                 }
             } else {
-                eprintln!(
+                writeln!(
+                    ctx.stderr,
                     "rm: cannot remove '{}': Not a regular file or directory",
                     elem
-                );
+                )?;
+                status = 1;
             }
         }
 
-        Ok(())
+        Ok(status)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-r"]
     }
 }
@@ -0,0 +1,71 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+use crate::options::Options;
+use crate::variables::quote;
+
+/// `set` lists the shell variables; `set -o name` and `set +o name` turn
+/// shell options on and off, and `set -o`/`set +o` alone show them.
+/// `set -- arg ...` makes the args the positional parameters, and `set --`
+/// alone clears them.
+pub struct SetCommand;
+
+impl Command for SetCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let mut args = args.iter();
+
+        let Some(flag) = args.next() else {
+            for (name, value) in ctx.shell.vars().iter() {
+                writeln!(ctx.stdout, "{}={}", name, quote(value))?;
+            }
+            return Ok(0);
+        };
+
+        let enable = match flag.as_str() {
+            "--" => {
+                ctx.shell.set_positional(args.cloned().collect());
+                return Ok(0);
+            }
+            "-o" => true,
+            "+o" => false,
+            _ => return usage(ctx, flag),
+        };
+
+        let names: Vec<&String> = args.collect();
+        if names.is_empty() {
+            return list_options(ctx, enable);
+        }
+
+        for name in names {
+            if !ctx.shell.options_mut().set(name, enable) {
+                writeln!(ctx.stderr, "set: {}: invalid option name", name)?;
+                return Ok(1);
+            }
+        }
+
+        Ok(0)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-o", "+o"]
+    }
+}
+
+/// Lists the options: `set -o` as a table, `set +o` as the commands that
+/// restore them.
+fn list_options(ctx: &mut Context, table: bool) -> Result<i32, ShellError> {
+    for name in Options::NAMES {
+        let enabled = ctx.shell.options().get(name).unwrap_or(false);
+        if table {
+            writeln!(ctx.stdout, "{:<15} {}", name, if enabled { "on" } else { "off" })?;
+        } else {
+            writeln!(ctx.stdout, "set {}o {}", if enabled { '-' } else { '+' }, name)?;
+        }
+    }
+    Ok(0)
+}
+
+fn usage(ctx: &mut Context, arg: &str) -> Result<i32, ShellError> {
+    writeln!(ctx.stderr, "set: {}: invalid option", arg)?;
+    writeln!(ctx.stderr, "set: usage: set [-o name] [+o name] [-- arg ...]")?;
+    Ok(2)
+}
@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+
+use crate::commands::{Command, Context};
+use crate::error::*;
+use crate::exec;
+
+/// `source file [arg ...]` or `. file [arg ...]`: runs the commands of a
+/// file in the current shell, with the args as positional parameters if
+/// given. A name without a `/` is looked up in `PATH`, then in the current
+/// directory.
+pub struct SourceCommand;
+
+impl Command for SourceCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let Some(name) = args.first() else {
+            writeln!(ctx.stderr, "source: filename argument required")?;
+            writeln!(ctx.stderr, "source: usage: source filename [arguments]")?;
+            return Ok(2);
+        };
+
+        let path = find_file(name, ctx.var("PATH").as_deref());
+        ctx.stdout.flush()?;
+
+        let saved = (args.len() > 1).then(|| ctx.shell.set_positional(args[1..].to_vec()));
+        let result = ctx.shell.source(&path);
+        if let Some(saved) = saved {
+            ctx.shell.set_positional(saved);
+        }
+
+        match result {
+            Ok(status) => Ok(status),
+            Err(err) => {
+                writeln!(ctx.stderr, "source: {}", err)?;
+                Ok(1)
+            }
+        }
+    }
+}
+
+fn find_file(name: &str, path_var: Option<&str>) -> PathBuf {
+    if name.contains('/') {
+        return PathBuf::from(name);
+    }
+
+    exec::search_path(path_var)
+        .map(|dir| Path::new(dir).join(name))
+        .find(|candidate| candidate.is_file())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
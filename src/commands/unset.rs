@@ -0,0 +1,48 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+use crate::variables::is_valid_name;
+
+/// `unset [-v] name ...` removes variables, `unset -f name ...` functions.
+/// Without an option, a name that is not a variable removes the function.
+pub struct UnsetCommand;
+
+impl Command for UnsetCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        let mut variables = true;
+        let mut functions = true;
+        let mut names = Vec::new();
+
+        for arg in args {
+            match arg.as_str() {
+                "-v" if names.is_empty() => functions = false,
+                "-f" if names.is_empty() => variables = false,
+                _ => names.push(arg),
+            }
+        }
+
+        let mut status = 0;
+
+        for name in &names {
+            if functions && (!variables || ctx.shell.vars().get(name).is_none()) {
+                let removed = ctx.shell.functions_mut().remove(name).is_some();
+                if removed || !variables {
+                    continue;
+                }
+            }
+
+            if !is_valid_name(name) {
+                writeln!(ctx.stderr, "unset: `{}': not a valid identifier", name)?;
+                status = 1;
+                continue;
+            }
+
+            ctx.shell.vars_mut().unset(name);
+        }
+
+        Ok(status)
+    }
+
+    fn flags(&self) -> &'static [&'static str] {
+        &["-f", "-v"]
+    }
+}
@@ -0,0 +1,49 @@
+use crate::commands::{Command, Context};
+use crate::error::*;
+
+/// `wait [jobspec | pid ...]`: waits for the given jobs, or all of them,
+/// to finish. Reports the status of the last one waited for.
+pub struct WaitCommand;
+
+impl Command for WaitCommand {
+    fn execute(&self, ctx: &mut Context, args: Vec<String>) -> Result<i32, ShellError> {
+        if args.is_empty() {
+            let ids: Vec<usize> = ctx.shell.jobs().iter().map(|job| job.id).collect();
+            for id in ids {
+                ctx.shell.wait_job(id)?;
+            }
+            return Ok(0);
+        }
+
+        let mut status = 0;
+
+        for arg in args {
+            let id = if arg.starts_with('%') {
+                ctx.shell.jobs().find(&arg).ok()
+            } else {
+                match arg.parse() {
+                    Ok(pid) => ctx.shell.jobs().find_pid(pid),
+                    Err(_) => {
+                        writeln!(ctx.stderr, "wait: `{}': not a pid or valid job spec", arg)?;
+                        status = 2;
+                        continue;
+                    }
+                }
+            };
+
+            status = match id {
+                Some(id) => ctx.shell.wait_job(id)?,
+                None if arg.starts_with('%') => {
+                    writeln!(ctx.stderr, "wait: {}: no such job", arg)?;
+                    127
+                }
+                None => {
+                    writeln!(ctx.stderr, "wait: pid {} is not a child of this shell", arg)?;
+                    127
+                }
+            };
+        }
+
+        Ok(status)
+    }
+}
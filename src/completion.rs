@@ -0,0 +1,402 @@
+//! Tab completion at the prompt: command names, paths, the flags of
+//! builtins, and the arguments of commands with a completion spec set with
+//! `complete`.
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    fs,
+    path::Path,
+};
+
+use crate::{
+    editor::{Candidate, Completer, Completion},
+    exec,
+    shell::Shell,
+    variables::{quote, split_assignment},
+};
+
+/// The file the completion specs are kept in between sessions, in the home
+/// directory.
+pub const SPECS_FILE: &str = ".0shell_completions";
+
+/// How the arguments of a command are completed, set with `complete`.
+/// Candidates from every source given are offered together.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spec {
+    /// `-f`: file names.
+    pub files: bool,
+    /// `-d`: directory names.
+    pub directories: bool,
+    /// `-W`: a list of words, split on blanks.
+    pub words: Option<String>,
+    /// `-F`: a shell function printing candidates, one per line.
+    pub function: Option<String>,
+    /// `-C`: a command printing candidates, one per line.
+    pub command: Option<String>,
+}
+
+impl fmt::Display for Spec {
+    /// The options of `complete` that set this spec, as `complete -p`
+    /// shows them.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut options = Vec::new();
+        if self.files {
+            options.push("-f".to_string());
+        }
+        if self.directories {
+            options.push("-d".to_string());
+        }
+        if let Some(words) = &self.words {
+            options.push(format!("-W {}", quote(words)));
+        }
+        if let Some(function) = &self.function {
+            options.push(format!("-F {}", quote(function)));
+        }
+        if let Some(command) = &self.command {
+            options.push(format!("-C {}", quote(command)));
+        }
+        write!(f, "{}", options.join(" "))
+    }
+}
+
+/// Characters escaped with a backslash in an unquoted completion.
+const SPECIAL_CHARS: &[char] = &[
+    ' ', '\t', '\'', '"', '\\', '$', '`', '!', '*', '?', '[', ']', '{', '}', '(', ')', '&', ';',
+    '|', '<', '>', '#', '~',
+];
+
+impl Completer for Shell {
+    fn complete(&mut self, line: &str, cursor: usize) -> Completion {
+        let word = WordAt::find(line, cursor);
+
+        let spec = word.command().and_then(|name| self.completion(name)).cloned();
+
+        let candidates = if let Some(spec) = spec.filter(|_| !word.redirect_target) {
+            self.complete_spec(&spec, &word, line, cursor)
+        } else if word.is_command() {
+            if word.value.contains('/') {
+                complete_paths(&word.value, |path| path.is_dir() || exec::is_executable(path))
+            } else {
+                self.complete_commands(&word.value)
+            }
+        } else if word.value.starts_with(['-', '+'])
+            && let Some(flags) = word.command().and_then(|name| self.builtin(name))
+        {
+            flags
+                .flags()
+                .iter()
+                .filter(|flag| flag.starts_with(word.value.as_str()))
+                .map(|flag| (flag.to_string(), flag.to_string(), true))
+                .collect()
+        } else {
+            complete_paths(&word.value, |_| true)
+        };
+
+        Completion {
+            start: word.start,
+            quote: word.quote,
+            candidates: candidates
+                .into_iter()
+                .map(|(value, display, complete)| Candidate {
+                    replacement: quote_word(&value, word.quote),
+                    display,
+                    complete,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Shell {
+    /// The candidates `spec` gives for `word`.
+    fn complete_spec(
+        &mut self,
+        spec: &Spec,
+        word: &WordAt,
+        line: &str,
+        cursor: usize,
+    ) -> Vec<(String, String, bool)> {
+        let mut candidates = Vec::new();
+
+        if spec.files {
+            candidates.extend(complete_paths(&word.value, |_| true));
+        } else if spec.directories {
+            candidates.extend(complete_paths(&word.value, Path::is_dir));
+        }
+
+        let mut words: Vec<String> = Vec::new();
+        if let Some(list) = &spec.words {
+            words.extend(list.split_whitespace().map(str::to_string));
+        }
+        for generator in [&spec.function, &spec.command].into_iter().flatten() {
+            words.extend(self.run_generator(generator, word, line, cursor));
+        }
+
+        words.sort();
+        words.dedup();
+        candidates.extend(
+            words
+                .into_iter()
+                .filter(|candidate| candidate.starts_with(word.value.as_str()))
+                .map(|candidate| (candidate.clone(), candidate, true)),
+        );
+        candidates
+    }
+
+    /// Runs the function or command of a spec as `generator command word
+    /// previous`, with the line in `COMP_LINE` and the cursor position in
+    /// `COMP_POINT`, and returns the lines it prints.
+    fn run_generator(
+        &mut self,
+        generator: &str,
+        word: &WordAt,
+        line: &str,
+        cursor: usize,
+    ) -> Vec<String> {
+        let previous = word.previous.last().map_or("", String::as_str);
+        let source = format!(
+            "{} {} {} {}",
+            generator,
+            quote(word.command().unwrap_or_default()),
+            quote(&word.value),
+            quote(previous)
+        );
+
+        let saved = self.vars_mut().set_temporary(&[
+            ("COMP_LINE".to_string(), line.to_string()),
+            ("COMP_POINT".to_string(), cursor.to_string()),
+        ]);
+        let last_status = self.last_status();
+        let output = self.capture_output(&source);
+        self.set_last_status(last_status);
+        self.vars_mut().restore(saved);
+
+        match output {
+            Ok(output) => output.lines().map(str::to_string).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Aliases, functions, builtins and executables from `PATH` whose name
+    /// starts with `prefix`.
+    fn complete_commands(&self, prefix: &str) -> Vec<(String, String, bool)> {
+        let mut names: BTreeSet<String> = self
+            .aliases()
+            .keys()
+            .chain(self.functions().keys())
+            .map(String::as_str)
+            .chain(self.builtin_names())
+            .filter(|name| name.starts_with(prefix))
+            .map(str::to_string)
+            .collect();
+
+        let path_var = self.var("PATH");
+        for dir in exec::search_path(path_var.as_deref()) {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if name.starts_with(prefix) && exec::is_executable(&entry.path()) {
+                    names.insert(name);
+                }
+            }
+        }
+
+        names
+            .into_iter()
+            .map(|name| (name.clone(), name, true))
+            .collect()
+    }
+}
+
+/// Paths starting with `prefix` and accepted by `filter`, as `(path, name
+/// to list, complete)`: directories get a trailing `/` and are not
+/// complete, since their content may follow.
+fn complete_paths(prefix: &str, filter: impl Fn(&Path) -> bool) -> Vec<(String, String, bool)> {
+    let (dir, name_prefix) = match prefix.rfind('/') {
+        Some(index) => prefix.split_at(index + 1),
+        None => ("", prefix),
+    };
+    let Ok(entries) = fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<(String, String, bool)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Hidden entries only when asked for, as with globs.
+            if !name.starts_with(name_prefix) || (name.starts_with('.') && !name_prefix.starts_with('.')) {
+                return None;
+            }
+
+            let path = entry.path();
+            if !filter(&path) {
+                return None;
+            }
+
+            if path.is_dir() {
+                Some((format!("{}{}/", dir, name), format!("{}/", name), false))
+            } else {
+                Some((format!("{}{}", dir, name), name, true))
+            }
+        })
+        .collect();
+
+    candidates.sort();
+    candidates
+}
+
+/// Quotes a completed word so that the shell reads it back as `value`:
+/// within the quote it was started with, or with backslashes.
+fn quote_word(value: &str, quote: Option<char>) -> String {
+    match quote {
+        Some('\'') => format!("'{}", value.replace('\'', "'\\''")),
+        Some(quote) => {
+            let mut quoted = String::from(quote);
+            for ch in value.chars() {
+                if matches!(ch, '\\' | '$' | '"' | '`') {
+                    quoted.push('\\');
+                }
+                quoted.push(ch);
+            }
+            quoted
+        }
+        None => {
+            let mut quoted = String::new();
+            for ch in value.chars() {
+                if SPECIAL_CHARS.contains(&ch) {
+                    quoted.push('\\');
+                }
+                quoted.push(ch);
+            }
+            quoted
+        }
+    }
+}
+
+/// Reserved words followed by a command.
+const COMMAND_KEYWORDS: &[&str] = &["if", "then", "elif", "else", "while", "until", "do"];
+
+/// The word being completed, read from the line as the lexer would read it.
+#[derive(Debug, Default)]
+struct WordAt {
+    /// Where the word starts in the line, in characters.
+    start: usize,
+    /// The word with its quotes and backslashes removed.
+    value: String,
+    /// The quote left open at the cursor, if any.
+    quote: Option<char>,
+    /// The words before it in the same simple command.
+    previous: Vec<String>,
+    /// Whether the word is the target of a redirection.
+    redirect_target: bool,
+}
+
+impl WordAt {
+    fn find(line: &str, cursor: usize) -> Self {
+        let chars: Vec<char> = line.chars().take(cursor).collect();
+        let mut word = Self {
+            start: chars.len(),
+            ..Self::default()
+        };
+        let mut in_word = false;
+        let mut after_redirect = false;
+
+        let mut i = 0;
+        while i < chars.len() {
+            let ch = chars[i];
+            i += 1;
+
+            match word.quote {
+                Some('\'') if ch == '\'' => word.quote = None,
+                Some('"') if ch == '"' => word.quote = None,
+                Some('"') if ch == '\\' && matches!(chars.get(i), Some('\\' | '$' | '"' | '`')) => {
+                    word.value.push(chars[i]);
+                    i += 1;
+                }
+                Some(_) => word.value.push(ch),
+                None => {
+                    let separator = match ch {
+                        ' ' | '\t' | '\n' => Some(false),
+                        ';' | '|' | '(' | ')' => Some(true),
+                        '&' if chars.get(i) != Some(&'>') => Some(true),
+                        '<' | '>' | '&' => {
+                            // A number right before the operator is the fd.
+                            if in_word && word.value.chars().all(|c| c.is_ascii_digit()) {
+                                in_word = false;
+                                word.value.clear();
+                            }
+                            word.end(&mut in_word, &mut after_redirect);
+                            after_redirect = true;
+                            continue;
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(ends_command) = separator {
+                        word.end(&mut in_word, &mut after_redirect);
+                        if ends_command {
+                            word.previous.clear();
+                            after_redirect = false;
+                        }
+                        continue;
+                    }
+
+                    if !in_word {
+                        in_word = true;
+                        word.start = i - 1;
+                        word.redirect_target = after_redirect;
+                    }
+                    match ch {
+                        '\'' | '"' => word.quote = Some(ch),
+                        '\\' if i < chars.len() => {
+                            word.value.push(chars[i]);
+                            i += 1;
+                        }
+                        _ => word.value.push(ch),
+                    }
+                }
+            }
+        }
+
+        if !in_word {
+            word.start = chars.len();
+            word.redirect_target = after_redirect;
+        }
+        word
+    }
+
+    /// Ends the word being read, moving it to `previous` unless it was the
+    /// target of a redirection.
+    fn end(&mut self, in_word: &mut bool, after_redirect: &mut bool) {
+        if !*in_word {
+            return;
+        }
+        *in_word = false;
+
+        let value = std::mem::take(&mut self.value);
+        if *after_redirect {
+            *after_redirect = false;
+        } else if self.previous.is_empty() && COMMAND_KEYWORDS.contains(&value.as_str()) {
+            // A command follows these, as at the start of the line.
+        } else {
+            self.previous.push(value);
+        }
+    }
+
+    /// The command name of the simple command, after its assignments.
+    fn command(&self) -> Option<&str> {
+        self.previous
+            .iter()
+            .find(|word| split_assignment(word).is_none())
+            .map(String::as_str)
+    }
+
+    /// Whether the word is in command position.
+    fn is_command(&self) -> bool {
+        !self.redirect_target && self.command().is_none() && split_assignment(&self.value).is_none()
+    }
+}
@@ -0,0 +1,143 @@
+use std::io;
+
+/// A key press, decoded from the bytes the terminal sends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Char(char),
+    /// Ctrl with a letter, given in lower case.
+    Ctrl(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    WordLeft,
+    WordRight,
+    Escape,
+    /// A sequence we do not handle.
+    Unknown,
+}
+
+/// How long to wait for the rest of an escape sequence before taking a
+/// lone Escape key.
+const ESCAPE_TIMEOUT_MS: i32 = 50;
+
+/// Reads one key from fd 0, which must be in raw mode. `None` at end of
+/// input.
+pub fn read_key() -> io::Result<Option<Key>> {
+    let Some(byte) = read_byte()? else {
+        return Ok(None);
+    };
+
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x1b => read_escape()?,
+        1..=26 => Key::Ctrl((b'a' + byte - 1) as char),
+        0..=0x1f => Key::Unknown,
+        0x20..=0x7e => Key::Char(byte as char),
+        _ => read_utf8(byte)?,
+    };
+
+    Ok(Some(key))
+}
+
+/// Decodes what follows an ESC: `ESC [ ...` and `ESC O ...` sequences for
+/// the arrows and friends, and Alt+B/Alt+F word jumps.
+fn read_escape() -> io::Result<Key> {
+    let Some(byte) = read_byte_timeout(ESCAPE_TIMEOUT_MS)? else {
+        return Ok(Key::Escape);
+    };
+
+    match byte {
+        b'b' => return Ok(Key::WordLeft),
+        b'f' => return Ok(Key::WordRight),
+        b'[' | b'O' => {}
+        _ => return Ok(Key::Unknown),
+    }
+
+    // Parameters, then the final byte: `ESC [ 3 ~`, `ESC [ 1 ; 5 C`.
+    let mut params = String::new();
+    let final_byte = loop {
+        match read_byte_timeout(ESCAPE_TIMEOUT_MS)? {
+            Some(b @ (b'0'..=b'9' | b';')) => params.push(b as char),
+            Some(b) => break b,
+            None => return Ok(Key::Unknown),
+        }
+    };
+
+    let ctrl = params.ends_with(";5") || params.ends_with(";3");
+    let key = match (final_byte, params.as_str()) {
+        (b'A', _) => Key::Up,
+        (b'B', _) => Key::Down,
+        (b'C', _) if ctrl => Key::WordRight,
+        (b'D', _) if ctrl => Key::WordLeft,
+        (b'C', _) => Key::Right,
+        (b'D', _) => Key::Left,
+        (b'H', _) | (b'~', "1" | "7") => Key::Home,
+        (b'F', _) | (b'~', "4" | "8") => Key::End,
+        (b'~', "3") => Key::Delete,
+        _ => Key::Unknown,
+    };
+
+    Ok(key)
+}
+
+/// Reads the rest of a multi-byte UTF-8 character.
+fn read_utf8(first: u8) -> io::Result<Key> {
+    let len = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Ok(Key::Unknown),
+    };
+
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        match read_byte()? {
+            Some(byte) => bytes.push(byte),
+            None => return Ok(Key::Unknown),
+        }
+    }
+
+    Ok(std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|text| text.chars().next())
+        .map_or(Key::Unknown, Key::Char))
+}
+
+fn read_byte() -> io::Result<Option<u8>> {
+    let mut byte = 0u8;
+
+    loop {
+        match unsafe { libc::read(0, &mut byte as *mut u8 as *mut libc::c_void, 1) } {
+            0 => return Ok(None),
+            1 => return Ok(Some(byte)),
+            _ => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+fn read_byte_timeout(timeout_ms: i32) -> io::Result<Option<u8>> {
+    let mut fds = libc::pollfd {
+        fd: 0,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    if unsafe { libc::poll(&mut fds, 1, timeout_ms) } <= 0 {
+        return Ok(None);
+    }
+    read_byte()
+}
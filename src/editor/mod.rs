@@ -0,0 +1,521 @@
+//! The line editor used at the prompt when stdin is a terminal: the
+//! terminal is put in raw mode and every key is handled here, for cursor
+//! movement, editing shortcuts, history recall and search, and Tab
+//! completion.
+
+use std::io::{self, Write};
+
+use terminal_size::{Width, terminal_size};
+
+use crate::{commands::ls::LsOutput, utils::strip_ansi_codes};
+
+mod keys;
+mod search;
+
+use keys::{Key, read_key};
+use search::Search;
+
+/// Above this many candidates, ask before listing them.
+const LIST_CONFIRM_THRESHOLD: usize = 100;
+
+/// Supplies the candidates for Tab completion.
+pub trait Completer {
+    /// Completes the word that ends at `cursor`, counted in characters.
+    fn complete(&mut self, line: &str, cursor: usize) -> Completion;
+}
+
+/// The candidates for the word before the cursor.
+#[derive(Debug, Default)]
+pub struct Completion {
+    /// Where the word starts, in characters. Candidates replace the text
+    /// from there to the cursor.
+    pub start: usize,
+    /// The quote to close the word with once it is complete, when it was
+    /// started inside quotes.
+    pub quote: Option<char>,
+    pub candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// The text replacing the word, quoted as the shell needs it.
+    pub replacement: String,
+    /// The name shown when listing the candidates.
+    pub display: String,
+    /// Whether nothing more can follow, as for a file name but not for a
+    /// directory: a space is added after a complete word.
+    pub complete: bool,
+}
+
+/// Puts the terminal in raw mode, restoring the previous modes when
+/// dropped.
+struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let mut original = unsafe { std::mem::zeroed::<libc::termios>() };
+        if unsafe { libc::tcgetattr(0, &mut original) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+
+        if unsafe { libc::tcsetattr(0, libc::TCSADRAIN, &raw) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(0, libc::TCSADRAIN, &self.original) };
+    }
+}
+
+/// Reads a line with editing, `history` being the previous lines, oldest
+/// first, searched with fuzzy matching when `fuzzy_search` is set, and
+/// `completer` providing Tab completion. Returns `None` on Ctrl+D at an
+/// empty line, and an `Interrupted` error on Ctrl+C.
+pub fn read_line(
+    prompt: &str,
+    history: &[String],
+    fuzzy_search: bool,
+    completer: &mut dyn Completer,
+) -> io::Result<Option<String>> {
+    let _raw_mode = RawMode::enable()?;
+    let mut editor = Editor::new(prompt, history, fuzzy_search, completer);
+
+    editor.refresh()?;
+    editor.run()
+}
+
+struct Editor<'a> {
+    prompt: &'a str,
+    prompt_width: usize,
+    buffer: Vec<char>,
+    /// Position of the cursor in `buffer`.
+    cursor: usize,
+    /// Terminal row the cursor is on, counted from the prompt's first row.
+    cursor_row: usize,
+    history: &'a [String],
+    /// The history entry shown, `history.len()` for the line being typed.
+    history_index: usize,
+    /// The line being typed, kept aside while browsing the history.
+    draft: Vec<char>,
+    /// The Ctrl+R search in progress.
+    search: Option<Search>,
+    fuzzy_search: bool,
+    completer: &'a mut dyn Completer,
+    /// Whether the previous key was a Tab that left several candidates: a
+    /// second one lists them.
+    tab_pending: bool,
+    out: io::Stdout,
+}
+
+impl<'a> Editor<'a> {
+    fn new(
+        prompt: &'a str,
+        history: &'a [String],
+        fuzzy_search: bool,
+        completer: &'a mut dyn Completer,
+    ) -> Self {
+        Self {
+            prompt,
+            prompt_width: strip_ansi_codes(prompt).chars().count(),
+            buffer: Vec::new(),
+            cursor: 0,
+            cursor_row: 0,
+            history,
+            history_index: history.len(),
+            draft: Vec::new(),
+            search: None,
+            fuzzy_search,
+            completer,
+            tab_pending: false,
+            out: io::stdout(),
+        }
+    }
+
+    fn run(&mut self) -> io::Result<Option<String>> {
+        loop {
+            let Some(key) = read_key()? else {
+                return Ok(None);
+            };
+
+            let Some(key) = self.search_key(key) else {
+                self.refresh()?;
+                continue;
+            };
+
+            if key == Key::Tab {
+                self.complete()?;
+                continue;
+            }
+            self.tab_pending = false;
+
+            match key {
+                Key::Enter => {
+                    self.cursor = self.buffer.len();
+                    self.refresh()?;
+                    write!(self.out, "\r\n")?;
+                    self.out.flush()?;
+                    return Ok(Some(self.buffer.iter().collect()));
+                }
+                Key::Ctrl('c') => {
+                    self.cursor = self.buffer.len();
+                    self.refresh()?;
+                    write!(self.out, "^C\r")?;
+                    self.out.flush()?;
+                    return Err(io::ErrorKind::Interrupted.into());
+                }
+                Key::Ctrl('d') if self.buffer.is_empty() => {
+                    write!(self.out, "\r\n")?;
+                    self.out.flush()?;
+                    return Ok(None);
+                }
+                Key::Ctrl('d') | Key::Delete => {
+                    if self.cursor < self.buffer.len() {
+                        self.buffer.remove(self.cursor);
+                    }
+                }
+                Key::Backspace => {
+                    if self.cursor > 0 {
+                        self.cursor -= 1;
+                        self.buffer.remove(self.cursor);
+                    }
+                }
+                Key::Char(ch) => {
+                    self.buffer.insert(self.cursor, ch);
+                    self.cursor += 1;
+                }
+                Key::Left | Key::Ctrl('b') => self.cursor = self.cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => {
+                    self.cursor = (self.cursor + 1).min(self.buffer.len());
+                }
+                Key::Home | Key::Ctrl('a') => self.cursor = 0,
+                Key::End | Key::Ctrl('e') => self.cursor = self.buffer.len(),
+                Key::WordLeft => self.cursor = self.word_start(),
+                Key::WordRight => self.cursor = self.word_end(),
+                Key::Ctrl('k') => self.buffer.truncate(self.cursor),
+                Key::Ctrl('u') => {
+                    self.buffer.drain(..self.cursor);
+                    self.cursor = 0;
+                }
+                Key::Ctrl('w') => {
+                    let start = self.word_start();
+                    self.buffer.drain(start..self.cursor);
+                    self.cursor = start;
+                }
+                Key::Up | Key::Ctrl('p') => self.recall(self.history_index.checked_sub(1)),
+                Key::Down | Key::Ctrl('n') => self.recall(Some(self.history_index + 1)),
+                Key::Ctrl('r') => self.search = Some(Search::new(self.buffer.clone(), self.cursor)),
+                Key::Ctrl('l') => {
+                    write!(self.out, "\x1b[H\x1b[2J")?;
+                    self.cursor_row = 0;
+                }
+                _ => continue,
+            }
+
+            self.refresh()?;
+        }
+    }
+
+    /// Handles a key typed during a Ctrl+R search. Keys that do not drive
+    /// the search end it, keeping the line found, and are returned to be
+    /// handled as usual: Enter runs the line found.
+    fn search_key(&mut self, key: Key) -> Option<Key> {
+        let Some(search) = self.search.as_mut() else {
+            return Some(key);
+        };
+        let last = self.history.len().checked_sub(1);
+
+        match key {
+            Key::Char(ch) => {
+                search.query.push(ch);
+                search.find(self.history, search.found.or(last), self.fuzzy_search);
+            }
+            Key::Backspace => {
+                search.query.pop();
+                search.found = None;
+                search.failed = false;
+                if !search.query.is_empty() {
+                    search.find(self.history, last, self.fuzzy_search);
+                }
+            }
+            Key::Ctrl('r') if !search.query.is_empty() => {
+                let start = match search.found {
+                    Some(index) => index.checked_sub(1),
+                    None => last,
+                };
+                search.find(self.history, start, self.fuzzy_search);
+            }
+            Key::Ctrl('r') => {}
+            Key::Ctrl('g') => {
+                let search = self.search.take()?;
+                (self.buffer, self.cursor) = search.saved;
+            }
+            _ => {
+                let search = self.search.take()?;
+                if let Some(index) = search.found {
+                    if self.history_index == self.history.len() {
+                        self.draft = search.saved.0;
+                    }
+                    self.buffer = self.history[index].chars().collect();
+                    self.cursor = search.position;
+                    self.history_index = index;
+                }
+                return Some(key);
+            }
+        }
+
+        None
+    }
+
+    /// Completes the word before the cursor: a single candidate replaces
+    /// it, several ones extend it to their common prefix, and a second Tab
+    /// lists them when that is all there is.
+    fn complete(&mut self) -> io::Result<()> {
+        let line: String = self.buffer.iter().collect();
+        let completion = self.completer.complete(&line, self.cursor);
+        let start = completion.start.min(self.cursor);
+
+        let text = match completion.candidates.as_slice() {
+            [] => None,
+            [candidate] => {
+                let mut text = candidate.replacement.clone();
+                if candidate.complete {
+                    text.extend(completion.quote);
+                    text.push(' ');
+                }
+                Some(text)
+            }
+            candidates => {
+                let prefix = common_prefix(candidates);
+                let word: String = self.buffer[start..self.cursor].iter().collect();
+                (prefix.chars().count() > word.chars().count()).then_some(prefix)
+            }
+        };
+
+        match text {
+            Some(text) => {
+                let text: Vec<char> = text.chars().collect();
+                self.buffer.splice(start..self.cursor, text.iter().copied());
+                self.cursor = start + text.len();
+                // After a partial completion, the next Tab lists what is left.
+                self.tab_pending = completion.candidates.len() > 1;
+            }
+            None if self.tab_pending && !completion.candidates.is_empty() => {
+                self.list(&completion.candidates)?;
+                self.tab_pending = false;
+            }
+            None => {
+                write!(self.out, "\x07")?;
+                self.tab_pending = true;
+            }
+        }
+
+        self.refresh()
+    }
+
+    /// Shows the candidates in columns below the line, which is then drawn
+    /// again.
+    fn list(&mut self, candidates: &[Candidate]) -> io::Result<()> {
+        let cursor = self.cursor;
+        self.cursor = self.buffer.len();
+        self.refresh()?;
+        self.cursor = cursor;
+        write!(self.out, "\r\n")?;
+
+        if candidates.len() > LIST_CONFIRM_THRESHOLD {
+            write!(self.out, "Display all {} possibilities? (y or n)", candidates.len())?;
+            self.out.flush()?;
+            let answer = read_key()?;
+            write!(self.out, "\r\n")?;
+            if !matches!(answer, Some(Key::Char('y' | 'Y' | ' '))) {
+                self.cursor_row = 0;
+                return Ok(());
+            }
+        }
+
+        let columns = match terminal_size() {
+            Some((Width(width), _)) if width > 0 => width as usize,
+            _ => 80,
+        };
+        let names: Vec<Vec<String>> = candidates
+            .iter()
+            .map(|candidate| vec![candidate.display.clone()])
+            .collect();
+        let listing = LsOutput::format_result(&names, columns);
+
+        write!(self.out, "{}", listing.replace('\n', "\r\n"))?;
+        self.cursor_row = 0;
+        Ok(())
+    }
+
+    /// Where the word before the cursor starts, skipping blanks first.
+    fn word_start(&self) -> usize {
+        let mut i = self.cursor;
+        while i > 0 && self.buffer[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        while i > 0 && !self.buffer[i - 1].is_whitespace() {
+            i -= 1;
+        }
+        i
+    }
+
+    /// Where the word after the cursor ends, skipping blanks first.
+    fn word_end(&self) -> usize {
+        let mut i = self.cursor;
+        while i < self.buffer.len() && self.buffer[i].is_whitespace() {
+            i += 1;
+        }
+        while i < self.buffer.len() && !self.buffer[i].is_whitespace() {
+            i += 1;
+        }
+        i
+    }
+
+    /// Shows history entry `index`, past the last one being the line that
+    /// was being typed.
+    fn recall(&mut self, index: Option<usize>) {
+        let Some(index) = index.filter(|&index| index <= self.history.len()) else {
+            return;
+        };
+
+        if self.history_index == self.history.len() {
+            self.draft = self.buffer.clone();
+        }
+
+        self.buffer = match self.history.get(index) {
+            Some(entry) => entry.chars().collect(),
+            None => self.draft.clone(),
+        };
+        self.history_index = index;
+        self.cursor = self.buffer.len();
+    }
+
+    /// Redraws the prompt and the line, or the search and the entry found
+    /// during a Ctrl+R search.
+    fn refresh(&mut self) -> io::Result<()> {
+        let Some(search) = &self.search else {
+            let line = self.buffer.clone();
+            return self.draw(self.prompt, self.prompt_width, &line, self.cursor);
+        };
+
+        let prompt = search.prompt();
+        let (line, cursor) = match search.found {
+            Some(index) => (self.history[index].chars().collect(), search.position),
+            None => search.saved.clone(),
+        };
+        self.draw(&prompt, prompt.chars().count(), &line, cursor)
+    }
+
+    /// Draws `prompt` and `line`, which may span several terminal rows,
+    /// over what was shown before, and puts the cursor at `cursor` in the
+    /// line.
+    fn draw(
+        &mut self,
+        prompt: &str,
+        prompt_width: usize,
+        line: &[char],
+        cursor: usize,
+    ) -> io::Result<()> {
+        let columns = match terminal_size() {
+            Some((Width(width), _)) if width > 0 => width as usize,
+            _ => 80,
+        };
+
+        let mut output = String::new();
+        if self.cursor_row > 0 {
+            output.push_str(&format!("\x1b[{}A", self.cursor_row));
+        }
+        output.push_str("\r\x1b[J");
+        output.push_str(prompt);
+        for &ch in line {
+            // A command recalled from the history may span several lines.
+            if ch == '\n' {
+                output.push_str("\r\n");
+            } else {
+                output.push(ch);
+            }
+        }
+
+        // At the very end of a row the terminal only wraps on the next
+        // character: move to the next row explicitly.
+        let (end_row, end_column) = position(prompt_width, line, line.len(), columns);
+        if end_column == 0 && prompt_width + line.len() > 0 && line.last() != Some(&'\n') {
+            output.push_str("\r\n");
+        }
+
+        let (row, column) = position(prompt_width, line, cursor, columns);
+
+        if end_row > row {
+            output.push_str(&format!("\x1b[{}A", end_row - row));
+        }
+        output.push('\r');
+        if column > 0 {
+            output.push_str(&format!("\x1b[{}C", column));
+        }
+
+        self.cursor_row = row;
+        write!(self.out, "{}", output)?;
+        self.out.flush()
+    }
+}
+
+/// The row and column, counted from the prompt's row, at which `line`
+/// drawn after a prompt `prompt_width` wide shows its character `index`.
+fn position(prompt_width: usize, line: &[char], index: usize, columns: usize) -> (usize, usize) {
+    let (mut row, mut column) = (prompt_width / columns, prompt_width % columns);
+    // Whether the last character filled a row: a newline then only ends
+    // the wrap the terminal was about to make.
+    let mut wrapped = prompt_width > 0 && column == 0;
+    for &ch in &line[..index] {
+        if ch == '\n' {
+            if !wrapped {
+                row += 1;
+            }
+            column = 0;
+            wrapped = false;
+        } else {
+            column += 1;
+            wrapped = column == columns;
+            if wrapped {
+                row += 1;
+                column = 0;
+            }
+        }
+    }
+    (row, column)
+}
+
+/// The longest prefix the replacements of `candidates` share, without a
+/// dangling backslash that would escape whatever comes next.
+fn common_prefix(candidates: &[Candidate]) -> String {
+    let first = &candidates[0].replacement;
+    let mut length = first.len();
+    for candidate in &candidates[1..] {
+        length = first
+            .char_indices()
+            .zip(candidate.replacement.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((index, ch), _)| index + ch.len_utf8())
+            .min(length);
+    }
+
+    let prefix = &first[..length];
+    let backslashes = prefix.chars().rev().take_while(|&ch| ch == '\\').count();
+    match backslashes % 2 {
+        1 => prefix[..length - 1].to_string(),
+        _ => prefix.to_string(),
+    }
+}
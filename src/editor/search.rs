@@ -0,0 +1,81 @@
+//! Reverse incremental search through the history, started with Ctrl+R.
+
+/// The state of a search in progress.
+pub struct Search {
+    pub query: String,
+    /// The history entry matching the query, if any.
+    pub found: Option<usize>,
+    /// Where the match starts in that entry, in characters.
+    pub position: usize,
+    /// Whether the last attempt to find an entry failed.
+    pub failed: bool,
+    /// The line and cursor from before the search, put back on Ctrl+G.
+    pub saved: (Vec<char>, usize),
+}
+
+impl Search {
+    pub fn new(buffer: Vec<char>, cursor: usize) -> Self {
+        Self {
+            query: String::new(),
+            found: None,
+            position: 0,
+            failed: false,
+            saved: (buffer, cursor),
+        }
+    }
+
+    /// The prompt shown instead of the shell's while searching.
+    pub fn prompt(&self) -> String {
+        let failed = if self.failed { "failed " } else { "" };
+        format!("({}reverse-i-search)`{}': ", failed, self.query)
+    }
+
+    /// Looks for the query in `history`, from entry `start` back to the
+    /// oldest one. The current match is kept when nothing is found.
+    pub fn find(&mut self, history: &[String], start: Option<usize>, fuzzy: bool) {
+        let Some(start) = start.filter(|&start| start < history.len()) else {
+            self.failed = true;
+            return;
+        };
+
+        let found = history[..=start].iter().enumerate().rev().find_map(|(index, entry)| {
+            match_position(entry, &self.query, fuzzy).map(|position| (index, position))
+        });
+
+        match found {
+            Some((index, position)) => {
+                self.found = Some(index);
+                self.position = position;
+                self.failed = false;
+            }
+            None => self.failed = true,
+        }
+    }
+}
+
+/// Where `query` matches in `entry`, in characters. A fuzzy match only
+/// needs the characters of the query to appear in order, ignoring case,
+/// and is reported where the first one is.
+fn match_position(entry: &str, query: &str, fuzzy: bool) -> Option<usize> {
+    if !fuzzy {
+        let byte = entry.find(query)?;
+        return Some(entry[..byte].chars().count());
+    }
+
+    let mut wanted = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut first = None;
+    for (position, ch) in entry.chars().enumerate() {
+        let Some(&next) = wanted.peek() else {
+            break;
+        };
+        if ch.to_lowercase().eq(std::iter::once(next)) {
+            first.get_or_insert(position);
+            wanted.next();
+        }
+    }
+
+    match wanted.peek() {
+        None => Some(first.unwrap_or(0)),
+        Some(_) => None,
+    }
+}
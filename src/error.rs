@@ -2,12 +2,24 @@ use std::error;
 use std::fmt;
 use std::io;
 
+use crate::signals;
+
 #[derive(Debug)]
 pub enum ShellError {
     IoError(std::io::Error),
     CommandNotFound(String),
-    Backticks,
     ArgsNotFound(String),
+    Syntax(String),
+    /// The input ended in the middle of a command, inside a quote or after
+    /// a `|` for instance.
+    Incomplete,
+    Redirect(String, io::Error),
+    /// An arithmetic expression that could not be evaluated, and why.
+    Arithmetic(String, String),
+    /// A file the shell was asked to read, like a script or an rc file.
+    File(String, io::Error),
+    /// Ctrl+C stopped the command.
+    Interrupted,
     Other(String),
 }
 
@@ -16,17 +28,52 @@ impl fmt::Display for ShellError {
         match self {
             ShellError::IoError(err) => write!(f, "{}", err),
             ShellError::CommandNotFound(cmd) => write!(f, "command '{}' not found", cmd),
-            ShellError::Backticks => write!(f, "command substitution with backticks (`) is not supported in our mini shell"),
             ShellError::ArgsNotFound(cmd) => write!(f, "{}: missing file operand", cmd),
+            ShellError::Syntax(token) => write!(f, "syntax error near unexpected token `{}'", token),
+            ShellError::Incomplete => write!(f, "syntax error: unexpected end of file"),
+            ShellError::Redirect(target, err) => write!(f, "{}: {}", target, describe(err)),
+            ShellError::File(path, err) => write!(f, "{}: {}", path, describe(err)),
+            ShellError::Arithmetic(expression, message) => write!(f, "{}: {}", expression, message),
+            // The `^C` the terminal echoed says it all; reporting the error
+            // just moves to a fresh line.
+            ShellError::Interrupted => Ok(()),
             ShellError::Other(err) => write!(f, "{}", err)
         }
     }
 }
 
+impl ShellError {
+    /// The exit status a command failing with this error reports.
+    pub fn status(&self) -> i32 {
+        match self {
+            ShellError::CommandNotFound(_) => 127,
+            ShellError::Syntax(_) | ShellError::Incomplete => 2,
+            ShellError::Interrupted => signals::INTERRUPTED_STATUS,
+            _ => 1,
+        }
+    }
+}
+
 impl error::Error for ShellError {}
 
+/// The bare OS message ("Permission denied"), without the
+/// " (os error 13)" suffix `io::Error` adds.
+pub fn describe(err: &io::Error) -> String {
+    match err.raw_os_error() {
+        Some(code) => unsafe {
+            std::ffi::CStr::from_ptr(libc::strerror(code))
+                .to_string_lossy()
+                .into_owned()
+        },
+        None => err.to_string(),
+    }
+}
+
 impl From<io::Error> for ShellError {
     fn from(err: io::Error) -> Self {
-        ShellError::IoError(err)
+        match err.kind() {
+            io::ErrorKind::Interrupted if signals::interrupted() => ShellError::Interrupted,
+            _ => ShellError::IoError(err),
+        }
     }
 }
@@ -0,0 +1,150 @@
+use std::{
+    ffi::CStr,
+    fs,
+    io::{self, ErrorKind},
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::{
+            fs::PermissionsExt,
+            process::{CommandExt, ExitStatusExt},
+        },
+    },
+    path::{Path, PathBuf},
+    process::{self, ExitStatus},
+};
+
+use crate::{error::ShellError, variables::Variables};
+
+/// Resolves a command name to an executable path.
+///
+/// Names containing a `/` are used as-is, everything else is searched
+/// in the directories listed in `path_var`, the shell's `PATH`.
+pub fn find_executable(name: &str, path_var: Option<&str>) -> Option<PathBuf> {
+    if name.contains('/') {
+        let path = PathBuf::from(name);
+        return if path.exists() { Some(path) } else { None };
+    }
+
+    search_path(path_var)
+        .map(|dir| Path::new(dir).join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// The directories of `PATH`, in order, an empty entry meaning the current
+/// directory.
+pub fn search_path(path_var: Option<&str>) -> impl Iterator<Item = &str> {
+    path_var
+        .unwrap_or("/usr/local/bin:/usr/bin:/bin")
+        .split(':')
+        .map(|dir| if dir.is_empty() { "." } else { dir })
+}
+
+pub fn is_executable(path: &Path) -> bool {
+    match fs::metadata(path) {
+        Ok(metadata) => metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+/// Replaces the current (forked) process with an external program, whose
+/// environment is made of the exported `vars`.
+///
+/// Only returns control by exiting when the program cannot be started.
+pub fn exec_external(cmd: &str, args: &[String], vars: &Variables) -> ! {
+    let err = match find_executable(cmd, vars.get("PATH")) {
+        None => ShellError::CommandNotFound(cmd.to_string()),
+        Some(path) if path.is_dir() => ShellError::Other(format!("{}: Is a directory", cmd)),
+        Some(path) => {
+            let err = process::Command::new(&path)
+                .arg0(cmd)
+                .args(args)
+                .env_clear()
+                .envs(vars.exported())
+                .exec();
+            match err.kind() {
+                ErrorKind::PermissionDenied => {
+                    ShellError::Other(format!("{}: Permission denied", cmd))
+                }
+                _ => ShellError::Other(format!("{}: {}", cmd, err)),
+            }
+        }
+    };
+
+    eprintln!("{}", err);
+    match err {
+        ShellError::CommandNotFound(_) => process::exit(127),
+        _ => process::exit(126),
+    }
+}
+
+/// Forks the shell, returning `0` in the child and the child's pid in
+/// the parent.
+pub fn fork() -> Result<libc::pid_t, ShellError> {
+    match unsafe { libc::fork() } {
+        -1 => Err(ShellError::IoError(io::Error::last_os_error())),
+        pid => Ok(pid),
+    }
+}
+
+pub fn wait_pid(pid: libc::pid_t) -> Result<ExitStatus, ShellError> {
+    let mut status = 0;
+
+    loop {
+        if unsafe { libc::waitpid(pid, &mut status, 0) } != -1 {
+            return Ok(ExitStatus::from_raw(status));
+        }
+
+        let err = io::Error::last_os_error();
+        if err.kind() != ErrorKind::Interrupted {
+            return Err(ShellError::IoError(err));
+        }
+    }
+}
+
+/// Makes `target` (0, 1 or 2) refer to the same open file as `fd`.
+pub fn redirect_fd(fd: &impl AsRawFd, target: RawFd) -> io::Result<()> {
+    if unsafe { libc::dup2(fd.as_raw_fd(), target) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// The numeric status of a finished child, `128 + n` when it was killed by
+/// signal `n`.
+pub fn status_code(status: &ExitStatus) -> i32 {
+    match status.signal() {
+        Some(signal) => 128 + signal,
+        None => status.code().unwrap_or(1),
+    }
+}
+
+/// Describes how a child terminated when it did not exit normally,
+/// e.g. "Segmentation fault (core dumped)".
+pub fn describe_signal(status: &ExitStatus) -> Option<String> {
+    let signal = status.signal()?;
+
+    // SIGINT and SIGPIPE are expected ways for a child to stop.
+    if signal == libc::SIGINT || signal == libc::SIGPIPE {
+        return None;
+    }
+
+    let description = signal_description(signal);
+
+    if status.core_dumped() {
+        Some(format!("{} (core dumped)", description))
+    } else {
+        Some(description)
+    }
+}
+
+/// The name of a signal as `strsignal` gives it, e.g. "Terminated".
+pub fn signal_description(signal: i32) -> String {
+    unsafe {
+        let raw = libc::strsignal(signal);
+        if raw.is_null() {
+            format!("Killed by signal {}", signal)
+        } else {
+            CStr::from_ptr(raw).to_string_lossy().into_owned()
+        }
+    }
+}
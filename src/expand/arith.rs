@@ -0,0 +1,501 @@
+//! Integer arithmetic, for `$((...))`, `((...))`, `let` and `for ((...))`.
+//! Variables are named without a `$` and read as 0 when unset or empty; a
+//! value that is not a number is itself evaluated as an expression.
+
+use std::{iter::Peekable, str::Chars};
+
+use crate::{error::ShellError, shell::Shell};
+
+/// How deep variables may refer to other variables before we give up.
+const MAX_DEPTH: usize = 64;
+
+/// Operators, longest first so that `<<=` is not read as `<` `<=`.
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "**", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+=", "-=",
+    "*=", "/=", "%=", "&=", "|=", "^=", "+", "-", "*", "/", "%", "<", ">", "&", "|", "^", "!", "~",
+    "?", ":", "=", "(", ")", ",",
+];
+
+const ASSIGNMENT_OPERATORS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "<<=", ">>=", "&=", "|=", "^=",
+];
+
+/// Evaluates an arithmetic expression; an empty one is 0.
+pub fn evaluate(expression: &str, shell: &mut Shell) -> Result<i64, ShellError> {
+    evaluate_at(expression, shell, 0)
+}
+
+fn evaluate_at(expression: &str, shell: &mut Shell, depth: usize) -> Result<i64, ShellError> {
+    let error =
+        |message: &str| ShellError::Arithmetic(expression.trim().to_string(), message.to_string());
+
+    if depth > MAX_DEPTH {
+        return Err(error("expression recursion level exceeded"));
+    }
+
+    let tokens = tokenize(expression).map_err(error)?;
+    if tokens.is_empty() {
+        return Ok(0);
+    }
+
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+    };
+    let expr = parser.comma().map_err(error)?;
+    if let Some(token) = parser.tokens.next() {
+        return Err(error(&format!(
+            "syntax error in expression (error token is \"{}\")",
+            token
+        )));
+    }
+
+    Evaluator { shell, depth }
+        .eval(&expr)
+        .map_err(|err| match err {
+            Failure::Message(message) => error(message),
+            Failure::Nested(err) => err,
+        })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Operator(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Operator(op) => write!(f, "{}", op),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, &'static str> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&ch) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+        } else if ch.is_ascii_digit() {
+            tokens.push(Token::Number(number(&mut chars)?));
+        } else if ch.is_alphabetic() || ch == '_' {
+            tokens.push(Token::Name(name(&mut chars)));
+        } else if ch == '$' {
+            // `$name` and `${name}` are the same as `name`.
+            chars.next();
+            let braced = chars.next_if_eq(&'{').is_some();
+            let name = match chars.peek() {
+                Some(&ch) if ch.is_ascii_digit() || "?#$!".contains(ch) => {
+                    chars.next();
+                    ch.to_string()
+                }
+                Some(&ch) if ch.is_alphabetic() || ch == '_' => name(&mut chars),
+                _ => return Err("syntax error: operand expected"),
+            };
+            if braced && chars.next_if_eq(&'}').is_none() {
+                return Err("bad substitution");
+            }
+            tokens.push(Token::Name(name));
+        } else {
+            let rest: String = chars.clone().take(3).collect();
+            let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) else {
+                return Err("syntax error: invalid arithmetic operator");
+            };
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push(Token::Operator(op));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// A decimal number, a hexadecimal one after `0x`, or an octal one after
+/// `0`.
+fn number(chars: &mut Peekable<Chars>) -> Result<i64, &'static str> {
+    let mut text = String::new();
+    while let Some(ch) = chars.next_if(|ch| ch.is_ascii_alphanumeric() || *ch == '_') {
+        text.push(ch);
+    }
+
+    let (digits, radix) = if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        (hex, 16)
+    } else if text.len() > 1 && text.starts_with('0') {
+        (&text[1..], 8)
+    } else {
+        (text.as_str(), 10)
+    };
+
+    u64::from_str_radix(digits, radix)
+        .map(|value| value as i64)
+        .map_err(|_| "value too great for base")
+}
+
+fn name(chars: &mut Peekable<Chars>) -> String {
+    let mut name = String::new();
+    while let Some(ch) = chars.next_if(|ch| ch.is_alphanumeric() || *ch == '_') {
+        name.push(ch);
+    }
+    name
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(i64),
+    Variable(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// `name op= value`, `op` being `=` for a plain assignment.
+    Assign(String, &'static str, Box<Expr>),
+    /// `++name`, `name--`...: the change and whether the new value is the
+    /// result.
+    Increment(String, i64, bool),
+}
+
+/// A recursive descent parser, from the lowest precedence to the highest.
+struct Parser {
+    tokens: Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    /// `assignment (',' assignment)*`
+    fn comma(&mut self) -> Result<Expr, &'static str> {
+        let mut expr = self.assignment()?;
+        while self.operator(",") {
+            expr = Expr::Binary(",", Box::new(expr), Box::new(self.assignment()?));
+        }
+        Ok(expr)
+    }
+
+    /// `conditional (assignment_operator assignment)?`
+    fn assignment(&mut self) -> Result<Expr, &'static str> {
+        let expr = self.conditional()?;
+
+        let Some(&Token::Operator(op)) = self.tokens.peek() else {
+            return Ok(expr);
+        };
+        if !ASSIGNMENT_OPERATORS.contains(&op) {
+            return Ok(expr);
+        }
+        self.tokens.next();
+
+        let Expr::Variable(name) = expr else {
+            return Err("attempted assignment to non-variable");
+        };
+        Ok(Expr::Assign(name, op, Box::new(self.assignment()?)))
+    }
+
+    /// `binary ('?' assignment ':' conditional)?`
+    fn conditional(&mut self) -> Result<Expr, &'static str> {
+        let condition = self.binary(1)?;
+        if !self.operator("?") {
+            return Ok(condition);
+        }
+
+        let then = self.assignment()?;
+        if !self.operator(":") {
+            return Err("`:' expected for conditional expression");
+        }
+        let otherwise = self.conditional()?;
+        Ok(Expr::Conditional(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    /// Binary operators of precedence `min` or more, by precedence climbing.
+    fn binary(&mut self, min: u8) -> Result<Expr, &'static str> {
+        let mut lhs = self.unary()?;
+
+        while let Some(&Token::Operator(op)) = self.tokens.peek() {
+            let Some(precedence) = precedence(op).filter(|&precedence| precedence >= min) else {
+                break;
+            };
+            self.tokens.next();
+
+            // `**` is right associative.
+            let next = if op == "**" {
+                precedence
+            } else {
+                precedence + 1
+            };
+            let rhs = self.binary(next)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, &'static str> {
+        match self.tokens.peek() {
+            Some(&Token::Operator(op @ ("-" | "+" | "!" | "~"))) => {
+                self.tokens.next();
+                Ok(Expr::Unary(op, Box::new(self.unary()?)))
+            }
+            Some(&Token::Operator(op @ ("++" | "--"))) => {
+                self.tokens.next();
+                let Some(Token::Name(name)) = self.tokens.next() else {
+                    return Err("syntax error: operand expected");
+                };
+                Ok(Expr::Increment(name, if op == "++" { 1 } else { -1 }, true))
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr, &'static str> {
+        let expr = self.primary()?;
+
+        if let Expr::Variable(name) = &expr
+            && let Some(&Token::Operator(op @ ("++" | "--"))) = self.tokens.peek()
+        {
+            self.tokens.next();
+            return Ok(Expr::Increment(
+                name.clone(),
+                if op == "++" { 1 } else { -1 },
+                false,
+            ));
+        }
+
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, &'static str> {
+        match self.tokens.next() {
+            Some(Token::Number(number)) => Ok(Expr::Number(number)),
+            Some(Token::Name(name)) => Ok(Expr::Variable(name)),
+            Some(Token::Operator("(")) => {
+                let expr = self.comma()?;
+                if !self.operator(")") {
+                    return Err("missing `)'");
+                }
+                Ok(expr)
+            }
+            _ => Err("syntax error: operand expected"),
+        }
+    }
+
+    /// Takes the operator `op` if it comes next.
+    fn operator(&mut self, op: &str) -> bool {
+        self.tokens
+            .next_if(|token| matches!(token, Token::Operator(found) if *found == op))
+            .is_some()
+    }
+}
+
+fn precedence(op: &str) -> Option<u8> {
+    Some(match op {
+        "||" => 1,
+        "&&" => 2,
+        "|" => 3,
+        "^" => 4,
+        "&" => 5,
+        "==" | "!=" => 6,
+        "<" | "<=" | ">" | ">=" => 7,
+        "<<" | ">>" => 8,
+        "+" | "-" => 9,
+        "*" | "/" | "%" => 10,
+        "**" => 11,
+        _ => return None,
+    })
+}
+
+/// Why evaluation stopped: an error in this expression, or one from the
+/// value of a variable, reported as is.
+enum Failure {
+    Message(&'static str),
+    Nested(ShellError),
+}
+
+struct Evaluator<'a> {
+    shell: &'a mut Shell,
+    depth: usize,
+}
+
+impl Evaluator<'_> {
+    fn eval(&mut self, expr: &Expr) -> Result<i64, Failure> {
+        match expr {
+            Expr::Number(number) => Ok(*number),
+            Expr::Variable(name) => self.variable(name),
+            Expr::Unary(op, operand) => {
+                let value = self.eval(operand)?;
+                Ok(match *op {
+                    "-" => value.wrapping_neg(),
+                    "!" => (value == 0) as i64,
+                    "~" => !value,
+                    _ => value,
+                })
+            }
+            Expr::Binary("&&", lhs, rhs) => {
+                Ok((self.eval(lhs)? != 0 && self.eval(rhs)? != 0) as i64)
+            }
+            Expr::Binary("||", lhs, rhs) => {
+                Ok((self.eval(lhs)? != 0 || self.eval(rhs)? != 0) as i64)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs)?;
+                let rhs = self.eval(rhs)?;
+                apply(op, lhs, rhs)
+            }
+            Expr::Conditional(condition, then, otherwise) => {
+                if self.eval(condition)? != 0 {
+                    self.eval(then)
+                } else {
+                    self.eval(otherwise)
+                }
+            }
+            Expr::Assign(name, op, value) => {
+                let mut value = self.eval(value)?;
+                if let Some(op) = op.strip_suffix('=').filter(|op| !op.is_empty()) {
+                    value = apply(op, self.variable(name)?, value)?;
+                }
+                self.shell.set_var(name, &value.to_string());
+                Ok(value)
+            }
+            Expr::Increment(name, delta, prefix) => {
+                let old = self.variable(name)?;
+                let new = old.wrapping_add(*delta);
+                self.shell.set_var(name, &new.to_string());
+                Ok(if *prefix { new } else { old })
+            }
+        }
+    }
+
+    fn variable(&mut self, name: &str) -> Result<i64, Failure> {
+        let value = self.shell.var(name).unwrap_or_default();
+        if let Ok(number) = value.trim().parse() {
+            return Ok(number);
+        }
+        evaluate_at(&value, self.shell, self.depth + 1).map_err(Failure::Nested)
+    }
+}
+
+fn apply(op: &str, lhs: i64, rhs: i64) -> Result<i64, Failure> {
+    Ok(match op {
+        "+" => lhs.wrapping_add(rhs),
+        "-" => lhs.wrapping_sub(rhs),
+        "*" => lhs.wrapping_mul(rhs),
+        "/" | "%" if rhs == 0 => return Err(Failure::Message("division by 0")),
+        "/" => lhs.wrapping_div(rhs),
+        "%" => lhs.wrapping_rem(rhs),
+        "**" if rhs < 0 => return Err(Failure::Message("exponent less than 0")),
+        "**" => lhs.wrapping_pow(rhs.min(u32::MAX as i64) as u32),
+        "<<" => lhs.wrapping_shl(rhs as u32),
+        ">>" => lhs.wrapping_shr(rhs as u32),
+        "&" => lhs & rhs,
+        "|" => lhs | rhs,
+        "^" => lhs ^ rhs,
+        "==" => (lhs == rhs) as i64,
+        "!=" => (lhs != rhs) as i64,
+        "<" => (lhs < rhs) as i64,
+        "<=" => (lhs <= rhs) as i64,
+        ">" => (lhs > rhs) as i64,
+        ">=" => (lhs >= rhs) as i64,
+        "," => rhs,
+        _ => unreachable!("not a binary operator: {}", op),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shell() -> Shell {
+        Shell::new(false)
+    }
+
+    fn eval(expression: &str) -> i64 {
+        evaluate(expression, &mut shell()).unwrap()
+    }
+
+    fn error(expression: &str, shell: &mut Shell) -> String {
+        match evaluate(expression, shell) {
+            Err(ShellError::Arithmetic(_, message)) => message,
+            result => panic!("{}: not an arithmetic error: {:?}", expression, result),
+        }
+    }
+
+    #[test]
+    fn follows_precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7);
+        assert_eq!(eval("(1 + 2) * 3"), 9);
+        assert_eq!(eval("10 - 4 - 3"), 3);
+        assert_eq!(eval("-2 ** 2"), 4);
+        assert_eq!(eval("2 ** 3 ** 2"), 512);
+        assert_eq!(eval("1 << 2 + 1"), 8);
+        assert_eq!(eval("1 | 6 & 3"), 3);
+        assert_eq!(eval("1 < 2 == 1"), 1);
+        assert_eq!(eval("0 || 2 && 3"), 1);
+        assert_eq!(eval("1, 2, 3"), 3);
+        assert_eq!(eval(""), 0);
+    }
+
+    #[test]
+    fn picks_a_branch_with_the_ternary() {
+        assert_eq!(eval("1 ? 2 : 3"), 2);
+        assert_eq!(eval("0 ? 2 : 3"), 3);
+        assert_eq!(eval("0 ? 1 : 0 ? 2 : 3"), 3);
+
+        // The branch not taken is not evaluated.
+        let mut shell = shell();
+        assert_eq!(evaluate("1 ? 0 : (arith_a = 5)", &mut shell).unwrap(), 0);
+        assert_eq!(shell.var("arith_a"), None);
+    }
+
+    #[test]
+    fn assigns_variables() {
+        let mut shell = shell();
+        shell.set_var("arith_b", "5");
+        assert_eq!(evaluate("arith_b += 2", &mut shell).unwrap(), 7);
+        assert_eq!(evaluate("arith_b <<= 2", &mut shell).unwrap(), 28);
+        assert_eq!(evaluate("arith_c = arith_b / 4", &mut shell).unwrap(), 7);
+        assert_eq!(shell.var("arith_b").as_deref(), Some("28"));
+        assert_eq!(shell.var("arith_c").as_deref(), Some("7"));
+        assert_eq!(error("1 = 2", &mut shell), "attempted assignment to non-variable");
+    }
+
+    #[test]
+    fn increments_before_and_after() {
+        let mut shell = shell();
+        shell.set_var("arith_d", "1");
+        assert_eq!(evaluate("arith_d++", &mut shell).unwrap(), 1);
+        assert_eq!(shell.var("arith_d").as_deref(), Some("2"));
+        assert_eq!(evaluate("++arith_d", &mut shell).unwrap(), 3);
+        assert_eq!(evaluate("arith_d--", &mut shell).unwrap(), 3);
+        assert_eq!(evaluate("--arith_d", &mut shell).unwrap(), 1);
+        assert_eq!(shell.var("arith_d").as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn wraps_on_overflow() {
+        assert_eq!(eval("9223372036854775807 + 1"), i64::MIN);
+        assert_eq!(eval("-9223372036854775807 - 2"), i64::MAX);
+        assert_eq!(eval("2 ** 64"), 0);
+    }
+
+    #[test]
+    fn reports_division_by_0() {
+        let mut shell = shell();
+        assert_eq!(error("1 / 0", &mut shell), "division by 0");
+        assert_eq!(error("1 % (2 - 2)", &mut shell), "division by 0");
+    }
+
+    #[test]
+    fn stops_variables_that_refer_to_themselves() {
+        let mut shell = shell();
+        shell.set_var("arith_e", "arith_f + 1");
+        shell.set_var("arith_f", "2");
+        assert_eq!(evaluate("arith_e * 2", &mut shell).unwrap(), 6);
+
+        shell.set_var("arith_g", "arith_g");
+        assert_eq!(error("arith_g", &mut shell), "expression recursion level exceeded");
+    }
+}
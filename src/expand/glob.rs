@@ -0,0 +1,163 @@
+//! Pathname expansion: turns a pattern such as `src/*.rs` or `**/*.log`
+//! into the sorted list of existing paths it matches.
+//!
+//! Each `/`-separated component is matched against the entries of one
+//! directory. A `**` component stands for any number of directories,
+//! including none. Names starting with `.` only match a component that
+//! starts with a literal `.`, and `**` does not descend into them.
+
+use std::fs;
+
+use super::pattern;
+
+/// The paths matching `pattern`, sorted; empty when nothing matches.
+pub fn glob(pattern: &str) -> Vec<String> {
+    let components: Vec<&str> = pattern.split('/').collect();
+    let mut results = Vec::new();
+
+    expand(String::new(), &components, &mut results);
+
+    results.sort();
+    results.dedup();
+    results
+}
+
+/// Matches `components` below `prefix`, a directory path that is either
+/// empty or ends with `/`.
+fn expand(prefix: String, components: &[&str], results: &mut Vec<String>) {
+    let Some((&component, rest)) = components.split_first() else {
+        return;
+    };
+
+    if component == "**" {
+        return expand_recursive(prefix, rest, results);
+    }
+
+    if let Some(name) = pattern::literal(component) {
+        let path = format!("{}{}", prefix, name);
+        if rest.is_empty() {
+            if fs::symlink_metadata(&path).is_ok() {
+                results.push(path);
+            }
+        } else {
+            expand(path + "/", rest, results);
+        }
+        return;
+    }
+
+    for name in entries(&prefix) {
+        if is_hidden(&name) && !component.starts_with('.') && !component.starts_with("\\.") {
+            continue;
+        }
+        if !pattern::matches(component, &name) {
+            continue;
+        }
+
+        let path = format!("{}{}", prefix, name);
+        if rest.is_empty() {
+            results.push(path);
+        } else if fs::metadata(&path).is_ok_and(|metadata| metadata.is_dir()) {
+            expand(path + "/", rest, results);
+        }
+    }
+}
+
+/// `**`: `rest` is matched in `prefix` and in every directory below it.
+/// A trailing `**` matches every file and directory below `prefix`.
+fn expand_recursive(prefix: String, rest: &[&str], results: &mut Vec<String>) {
+    if !rest.is_empty() {
+        expand(prefix.clone(), rest, results);
+    }
+
+    for name in entries(&prefix) {
+        if is_hidden(&name) {
+            continue;
+        }
+
+        let path = format!("{}{}", prefix, name);
+        // Symbolic links to directories are not followed, so that loops
+        // cannot make the walk endless.
+        let is_dir = fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_dir());
+
+        if rest.is_empty() {
+            results.push(path.clone());
+        }
+        if is_dir {
+            expand_recursive(path + "/", rest, results);
+        }
+    }
+}
+
+/// The names in the directory `prefix`, the current one when empty.
+fn entries(prefix: &str) -> Vec<String> {
+    let dir = if prefix.is_empty() { "." } else { prefix };
+
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of its own for each test, removed when dropped.
+    struct TempDir(String);
+
+    impl TempDir {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("0shell-glob-{}-{}", std::process::id(), name));
+            for file in files {
+                let path = dir.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, "").unwrap();
+            }
+            Self(dir.display().to_string())
+        }
+
+        fn glob(&self, pattern: &str) -> Vec<String> {
+            glob(&format!("{}/{}", self.0, pattern))
+                .into_iter()
+                .map(|path| path[self.0.len() + 1..].to_string())
+                .collect()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn matches_names_in_a_directory() {
+        let dir = TempDir::new("names", &["b.rs", "a.rs", "c.txt", ".hidden.rs"]);
+        assert_eq!(dir.glob("*.rs"), ["a.rs", "b.rs"]);
+        assert_eq!(dir.glob("?.txt"), ["c.txt"]);
+        assert_eq!(dir.glob(".*.rs"), [".hidden.rs"]);
+        assert!(dir.glob("*.md").is_empty());
+    }
+
+    #[test]
+    fn matches_each_component() {
+        let dir = TempDir::new("components", &["src/main.rs", "src/lib.rs", "tests/it.rs"]);
+        assert_eq!(dir.glob("*/*.rs"), ["src/lib.rs", "src/main.rs", "tests/it.rs"]);
+        assert_eq!(dir.glob("src/m*"), ["src/main.rs"]);
+        assert_eq!(dir.glob("src"), ["src"]);
+    }
+
+    #[test]
+    fn double_star_matches_any_depth() {
+        let dir = TempDir::new("recursive", &["a.log", "x/b.log", "x/y/c.log", ".git/d.log"]);
+        assert_eq!(dir.glob("**/*.log"), ["a.log", "x/b.log", "x/y/c.log"]);
+    }
+}
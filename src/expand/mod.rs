@@ -0,0 +1,331 @@
+use crate::{
+    error::ShellError,
+    parser::{Param, ParamOp, Word, WordPart},
+    shell::Shell,
+};
+
+pub use arith::evaluate as evaluate_arithmetic;
+
+mod arith;
+mod glob;
+mod pattern;
+
+const DEFAULT_IFS: &str = " \t\n";
+
+/// A field being built, and the same text as a glob pattern in which the
+/// quoted characters are escaped.
+#[derive(Default)]
+struct Field {
+    text: String,
+    pattern: String,
+}
+
+/// Collects the fields a list of words expands to.
+struct Fields {
+    fields: Vec<Field>,
+    current: Field,
+    /// Whether the current field exists even if it is empty, as with `""`.
+    started: bool,
+    /// The separators for field splitting, `None` to keep a single field.
+    ifs: Option<String>,
+}
+
+impl Fields {
+    fn new(ifs: Option<String>) -> Self {
+        Self {
+            fields: Vec::new(),
+            current: Field::default(),
+            started: false,
+            ifs,
+        }
+    }
+
+    /// Pushes unquoted text, whose wildcards are kept for globbing.
+    fn push(&mut self, text: &str) {
+        self.current.text.push_str(text);
+        self.current.pattern.push_str(text);
+        self.started = true;
+    }
+
+    fn push_quoted(&mut self, text: &str) {
+        self.current.text.push_str(text);
+        self.current.pattern.push_str(&pattern::escape(text));
+        self.started = true;
+    }
+
+    /// Pushes the result of an unquoted expansion, which is split into
+    /// several fields on `IFS` characters.
+    fn push_split(&mut self, text: &str) {
+        let Some(ifs) = self.ifs.take() else {
+            return self.push(text);
+        };
+
+        for ch in text.chars() {
+            if ifs.contains(ch) {
+                self.end_field();
+            } else {
+                self.current.text.push(ch);
+                self.current.pattern.push(ch);
+                self.started = true;
+            }
+        }
+
+        self.ifs = Some(ifs);
+    }
+
+    fn end_field(&mut self) {
+        if self.started {
+            self.fields.push(std::mem::take(&mut self.current));
+            self.started = false;
+        }
+    }
+}
+
+/// Expands words into the arguments of a command. Unquoted expansions are
+/// split into separate fields, and those expanding to nothing disappear.
+/// Fields with unquoted wildcards are then replaced by the paths they match.
+pub fn expand_words(words: &[Word], shell: &mut Shell) -> Result<Vec<String>, ShellError> {
+    let ifs = shell.var("IFS").unwrap_or_else(|| DEFAULT_IFS.to_string());
+    let mut fields = Fields::new(Some(ifs));
+
+    for word in words {
+        expand_parts(&word.parts, false, shell, &mut fields)?;
+        fields.end_field();
+    }
+
+    let mut result = Vec::new();
+    for field in fields.fields {
+        if pattern::literal(&field.pattern).is_some() {
+            result.push(field.text);
+            continue;
+        }
+
+        let paths = glob::glob(&field.pattern);
+        if !paths.is_empty() {
+            result.extend(paths);
+        } else if shell.options().nomatch {
+            return Err(ShellError::Other(format!("no matches found: {}", field.text)));
+        } else {
+            result.push(field.text);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Expands a word into a single string, without field splitting, as for
+/// the target of a redirection.
+pub fn expand_word(word: &Word, shell: &mut Shell) -> Result<String, ShellError> {
+    let mut fields = Fields::new(None);
+    expand_parts(&word.parts, false, shell, &mut fields)?;
+    Ok(fields.current.text)
+}
+
+fn expand_parts(
+    parts: &[WordPart],
+    quoted: bool,
+    shell: &mut Shell,
+    fields: &mut Fields,
+) -> Result<(), ShellError> {
+    for part in parts {
+        match part {
+            WordPart::Literal(text) if !quoted => fields.push(text),
+            WordPart::Literal(text) | WordPart::Quoted(text) => fields.push_quoted(text),
+            WordPart::Param(param) => expand_param(param, quoted || param.quoted, shell, fields)?,
+            WordPart::Command {
+                source,
+                quoted: command_quoted,
+            } => {
+                let output = shell.capture_output(source)?;
+                let output = output.trim_end_matches('\n');
+                if quoted || *command_quoted {
+                    fields.push_quoted(output);
+                } else {
+                    fields.push_split(output);
+                }
+            }
+            WordPart::Arithmetic {
+                expression,
+                quoted: arithmetic_quoted,
+            } => {
+                let expression = expand_word(expression, shell)?;
+                let value = evaluate_arithmetic(&expression, shell)?.to_string();
+                if quoted || *arithmetic_quoted {
+                    fields.push_quoted(&value);
+                } else {
+                    fields.push_split(&value);
+                }
+            }
+            WordPart::BadSubstitution(text) => {
+                return Err(ShellError::Other(format!("{}: bad substitution", text)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn expand_param(
+    param: &Param,
+    quoted: bool,
+    shell: &mut Shell,
+    fields: &mut Fields,
+) -> Result<(), ShellError> {
+    // `"$@"` is one field per positional parameter, none when there are
+    // none.
+    if param.name == "@" && param.op == ParamOp::Value && quoted {
+        for (index, arg) in shell.positional().to_vec().iter().enumerate() {
+            if index > 0 {
+                fields.end_field();
+            }
+            fields.push_quoted(arg);
+        }
+        return Ok(());
+    }
+
+    let value = shell.var(&param.name);
+    // For the `:` forms an empty value counts as unset.
+    let is_unset = |colon: bool| match &value {
+        None => true,
+        Some(value) => colon && value.is_empty(),
+    };
+
+    let result = match &param.op {
+        ParamOp::Value => value.unwrap_or_default(),
+        ParamOp::Length => value.unwrap_or_default().chars().count().to_string(),
+        ParamOp::Default(word, colon) => {
+            if is_unset(*colon) {
+                return expand_operand(word, quoted, shell, fields);
+            }
+            value.unwrap_or_default()
+        }
+        ParamOp::Alternative(word, colon) => {
+            if !is_unset(*colon) {
+                return expand_operand(word, quoted, shell, fields);
+            }
+            String::new()
+        }
+        ParamOp::Assign(word, colon) => {
+            if is_unset(*colon) {
+                let default = expand_word(word, shell)?;
+                shell.set_var(&param.name, &default);
+                default
+            } else {
+                value.unwrap_or_default()
+            }
+        }
+        ParamOp::Error(word, colon) => {
+            if is_unset(*colon) {
+                let message = match expand_word(word, shell)? {
+                    message if message.is_empty() => "parameter null or not set".to_string(),
+                    message => message,
+                };
+                return Err(ShellError::Other(format!("{}: {}", param.name, message)));
+            }
+            value.unwrap_or_default()
+        }
+        ParamOp::TrimPrefix(word, longest) => {
+            let pattern = expand_pattern(word, shell)?;
+            trim_prefix(&value.unwrap_or_default(), &pattern, *longest)
+        }
+        ParamOp::TrimSuffix(word, longest) => {
+            let pattern = expand_pattern(word, shell)?;
+            trim_suffix(&value.unwrap_or_default(), &pattern, *longest)
+        }
+    };
+
+    if quoted {
+        fields.push_quoted(&result);
+    } else {
+        fields.push_split(&result);
+    }
+    Ok(())
+}
+
+/// Expands the word of `${name:-word}` or `${name:+word}` in place of the
+/// parameter. Its unquoted text is part of the expansion result, so unlike
+/// the literal text of a command line it undergoes field splitting.
+fn expand_operand(
+    word: &Word,
+    quoted: bool,
+    shell: &mut Shell,
+    fields: &mut Fields,
+) -> Result<(), ShellError> {
+    for part in &word.parts {
+        match part {
+            WordPart::Literal(text) if !quoted => fields.push_split(text),
+            _ => expand_parts(std::slice::from_ref(part), quoted, shell, fields)?,
+        }
+    }
+    Ok(())
+}
+
+/// Whether `value` matches `pattern`, a word expanded as a pattern, as
+/// with the patterns of `case`.
+pub fn match_pattern(value: &str, pattern: &Word, shell: &mut Shell) -> Result<bool, ShellError> {
+    let pattern = expand_pattern(pattern, shell)?;
+    Ok(pattern::matches(&pattern, value))
+}
+
+/// Expands a word used as a pattern: quoted characters lose their special
+/// meaning, so `${file%"*"}` only removes a literal star.
+fn expand_pattern(word: &Word, shell: &mut Shell) -> Result<String, ShellError> {
+    let mut result = String::new();
+
+    for part in &word.parts {
+        match part {
+            WordPart::Literal(text) => result.push_str(text),
+            WordPart::Quoted(text) => result.push_str(&pattern::escape(text)),
+            WordPart::Param(Param { quoted, .. })
+            | WordPart::Command { quoted, .. }
+            | WordPart::Arithmetic { quoted, .. } => {
+                let value = expand_word(
+                    &Word {
+                        parts: vec![part.clone()],
+                    },
+                    shell,
+                )?;
+                if *quoted {
+                    result.push_str(&pattern::escape(&value));
+                } else {
+                    result.push_str(&value);
+                }
+            }
+            WordPart::BadSubstitution(text) => {
+                return Err(ShellError::Other(format!("{}: bad substitution", text)));
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+fn char_boundaries(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect()
+}
+
+fn trim_prefix(value: &str, pattern: &str, longest: bool) -> String {
+    let mut boundaries = char_boundaries(value);
+    if longest {
+        boundaries.reverse();
+    }
+
+    boundaries
+        .into_iter()
+        .find(|&end| pattern::matches(pattern, &value[..end]))
+        .map_or_else(|| value.to_string(), |end| value[end..].to_string())
+}
+
+fn trim_suffix(value: &str, pattern: &str, longest: bool) -> String {
+    let mut boundaries = char_boundaries(value);
+    if !longest {
+        boundaries.reverse();
+    }
+
+    boundaries
+        .into_iter()
+        .find(|&start| pattern::matches(pattern, &value[start..]))
+        .map_or_else(|| value.to_string(), |start| value[..start].to_string())
+}
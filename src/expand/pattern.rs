@@ -0,0 +1,231 @@
+//! Shell pattern matching: `*`, `?`, bracket expressions such as `[a-z]`,
+//! `[!0-9]` or `[[:alpha:]]`, and `\` to take the next character literally.
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Char(char),
+    AnyChar,
+    AnyString,
+    Class { items: Vec<ClassItem>, negated: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+    Named(String),
+}
+
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let tokens = compile(pattern);
+    let text: Vec<char> = text.chars().collect();
+    match_tokens(&tokens, &text)
+}
+
+/// The text `pattern` matches when it has no wildcards, with its escapes
+/// removed; `None` when it needs matching.
+pub fn literal(pattern: &str) -> Option<String> {
+    compile(pattern)
+        .into_iter()
+        .map(|token| match token {
+            Token::Char(ch) => Some(ch),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Escapes the characters `matches` would treat specially.
+pub fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '*' | '?' | '[' | ']' | '\\') {
+            result.push('\\');
+        }
+        result.push(ch);
+    }
+    result
+}
+
+fn compile(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' => tokens.push(Token::AnyString),
+            '?' => tokens.push(Token::AnyChar),
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                tokens.push(Token::Char(chars[i]));
+            }
+            '[' => match compile_class(&chars[i + 1..]) {
+                Some((token, consumed)) => {
+                    tokens.push(token);
+                    i += consumed;
+                }
+                None => tokens.push(Token::Char('[')),
+            },
+            ch => tokens.push(Token::Char(ch)),
+        }
+        i += 1;
+    }
+
+    tokens
+}
+
+/// Parses a bracket expression following its `[`. Returns the token and the
+/// number of characters used, closing `]` included, or `None` when the
+/// bracket is never closed and must be taken literally.
+fn compile_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut items = Vec::new();
+    let mut i = 0;
+
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+
+    let start = i;
+    while i < chars.len() {
+        let ch = chars[i];
+
+        // A `]` right after `[` or `[!` is a member, not the end.
+        if ch == ']' && i > start {
+            return Some((Token::Class { items, negated }, i + 1));
+        }
+
+        if ch == '['
+            && chars.get(i + 1) == Some(&':')
+            && let Some(len) = chars[i + 2..]
+                .windows(2)
+                .position(|pair| pair == [':', ']'])
+        {
+            let name: String = chars[i + 2..i + 2 + len].iter().collect();
+            items.push(ClassItem::Named(name));
+            i += len + 4;
+            continue;
+        }
+
+        let ch = if ch == '\\' && i + 1 < chars.len() {
+            i += 1;
+            chars[i]
+        } else {
+            ch
+        };
+
+        if chars.get(i + 1) == Some(&'-')
+            && let Some(&end) = chars.get(i + 2)
+            && end != ']'
+        {
+            items.push(ClassItem::Range(ch, end));
+            i += 3;
+        } else {
+            items.push(ClassItem::Char(ch));
+            i += 1;
+        }
+    }
+
+    None
+}
+
+fn match_tokens(tokens: &[Token], text: &[char]) -> bool {
+    let (mut t, mut s) = (0, 0);
+    // Where to resume after the last `*`: the token after it and the text
+    // position it currently stands for.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while s < text.len() {
+        match tokens.get(t) {
+            Some(Token::AnyString) => {
+                backtrack = Some((t + 1, s));
+                t += 1;
+                continue;
+            }
+            Some(token) if token_matches(token, text[s]) => {
+                t += 1;
+                s += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        match backtrack {
+            Some((next_token, star_pos)) => {
+                t = next_token;
+                s = star_pos + 1;
+                backtrack = Some((next_token, star_pos + 1));
+            }
+            None => return false,
+        }
+    }
+
+    tokens[t..].iter().all(|token| *token == Token::AnyString)
+}
+
+fn token_matches(token: &Token, ch: char) -> bool {
+    match token {
+        Token::Char(expected) => *expected == ch,
+        Token::AnyChar => true,
+        Token::AnyString => false,
+        Token::Class { items, negated } => {
+            items.iter().any(|item| item_matches(item, ch)) != *negated
+        }
+    }
+}
+
+fn item_matches(item: &ClassItem, ch: char) -> bool {
+    match item {
+        ClassItem::Char(expected) => *expected == ch,
+        ClassItem::Range(start, end) => (*start..=*end).contains(&ch),
+        ClassItem::Named(name) => match name.as_str() {
+            "alpha" => ch.is_alphabetic(),
+            "digit" => ch.is_ascii_digit(),
+            "alnum" => ch.is_alphanumeric(),
+            "upper" => ch.is_uppercase(),
+            "lower" => ch.is_lowercase(),
+            "space" => ch.is_whitespace(),
+            "blank" => ch == ' ' || ch == '\t',
+            "punct" => ch.is_ascii_punctuation(),
+            "xdigit" => ch.is_ascii_hexdigit(),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("*.rs", ".rs"));
+        assert!(!matches("*.rs", "main.rc"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "aXXbYYc"));
+        assert!(!matches("a*b*c", "aXXbYY"));
+    }
+
+    #[test]
+    fn matches_bracket_expressions() {
+        assert!(matches("[a-c]x", "bx"));
+        assert!(!matches("[a-c]x", "dx"));
+        assert!(matches("[!0-9]", "a"));
+        assert!(!matches("[!0-9]", "5"));
+        assert!(matches("[[:alpha:]][[:digit:]]", "a1"));
+        assert!(!matches("[[:alpha:]]", "1"));
+    }
+
+    #[test]
+    fn backslash_makes_characters_literal() {
+        assert!(matches(r"\*", "*"));
+        assert!(!matches(r"\*", "a"));
+        assert_eq!(literal(r"a\*b"), Some("a*b".to_string()));
+        assert_eq!(literal("a*b"), None);
+        assert!(matches(&escape("[x]*"), "[x]*"));
+        assert!(!matches(&escape("[x]*"), "x"));
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+};
+
+/// The history file used when `HISTFILE` is not set, in the home directory.
+pub const DEFAULT_FILE: &str = ".0shell_history";
+
+/// How many lines are kept when `HISTSIZE` or `HISTFILESIZE` is not set.
+pub const DEFAULT_SIZE: usize = 500;
+
+/// Which lines stay out of the history, from `HISTCONTROL`: a
+/// colon-separated list of `ignorespace`, `ignoredups` and `ignoreboth`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Control {
+    /// Lines starting with a blank.
+    pub ignore_space: bool,
+    /// Lines identical to the previous entry.
+    pub ignore_dups: bool,
+}
+
+impl Control {
+    pub fn parse(value: &str) -> Self {
+        let mut control = Self::default();
+        for item in value.split(':') {
+            match item {
+                "ignorespace" => control.ignore_space = true,
+                "ignoredups" => control.ignore_dups = true,
+                "ignoreboth" => {
+                    control.ignore_space = true;
+                    control.ignore_dups = true;
+                }
+                _ => {}
+            }
+        }
+        control
+    }
+}
+
+/// The command lines entered at the prompt, oldest first. Entries are
+/// numbered from 1, as `history` lists them and `!n` recalls them.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+}
+
+impl History {
+    /// Adds a line, then drops the oldest entries beyond `size`.
+    pub fn add(&mut self, line: &str, control: Control, size: usize) {
+        if line.trim().is_empty() {
+            return;
+        }
+        if control.ignore_space && line.starts_with([' ', '\t']) {
+            return;
+        }
+        if control.ignore_dups && self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+
+        self.entries.push(line.to_string());
+        self.truncate(size);
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Removes entry `number`, counted from 1.
+    pub fn remove(&mut self, number: usize) -> Option<String> {
+        if number == 0 || number > self.entries.len() {
+            return None;
+        }
+        Some(self.entries.remove(number - 1))
+    }
+
+    /// Keeps only the last `size` entries.
+    fn truncate(&mut self, size: usize) {
+        if self.entries.len() > size {
+            self.entries.drain(..self.entries.len() - size);
+        }
+    }
+
+    /// Reads the entries saved in `path`, one per line, keeping the last
+    /// `size`. A missing file is an empty history. Commands typed on
+    /// several lines come back whole, see `escape`.
+    pub fn load(&mut self, path: &Path, size: usize) -> io::Result<()> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                self.entries.push(unescape(&line));
+            }
+        }
+        self.truncate(size);
+        Ok(())
+    }
+
+    /// Writes the last `size` entries to `path`, replacing its content.
+    pub fn save(&self, path: &Path, size: usize) -> io::Result<()> {
+        let start = self.entries.len().saturating_sub(size);
+        let mut content = String::new();
+        for entry in &self.entries[start..] {
+            content.push_str(&escape(entry));
+            content.push('\n');
+        }
+
+        // The history may hold secrets typed on the command line.
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(content.as_bytes())
+    }
+}
+
+/// An entry as saved on a line of its own: the newlines of a command typed
+/// on several lines become `\n`, and backslashes are doubled.
+fn escape(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// The entry saved as `line` by `escape`.
+fn unescape(line: &str) -> String {
+    let mut entry = String::new();
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            entry.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => entry.push('\n'),
+            Some('\\') => entry.push('\\'),
+            Some(next) => {
+                entry.push('\\');
+                entry.push(next);
+            }
+            None => entry.push('\\'),
+        }
+    }
+    entry
+}
@@ -0,0 +1,356 @@
+//! The job table: pipelines running in the background or stopped with
+//! Ctrl+Z, numbered from 1 and addressed with `%N` job specs.
+
+use std::{io, os::unix::process::ExitStatusExt, process::ExitStatus};
+
+use libc::pid_t;
+
+use crate::{exec, signals};
+
+#[derive(Debug, Clone)]
+pub struct Process {
+    pub pid: pid_t,
+    /// Set once the process has terminated.
+    pub status: Option<ExitStatus>,
+    pub stopped: bool,
+}
+
+impl Process {
+    pub fn new(pid: pid_t) -> Self {
+        Self {
+            pid,
+            status: None,
+            stopped: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobState {
+    Running,
+    Stopped,
+    Done,
+}
+
+#[derive(Clone)]
+pub struct Job {
+    /// The job number, 0 until the job enters the table.
+    pub id: usize,
+    /// The process group of the job, 0 without job control.
+    pub pgid: pid_t,
+    pub processes: Vec<Process>,
+    pub command: String,
+    /// The terminal modes the job had when it was stopped, given back to
+    /// it when it returns to the foreground.
+    pub termios: Option<libc::termios>,
+    /// Whether its current state has been reported to the user.
+    pub notified: bool,
+}
+
+impl Job {
+    pub fn new(pgid: pid_t, processes: Vec<Process>, command: String) -> Self {
+        Self {
+            id: 0,
+            pgid,
+            processes,
+            command,
+            termios: None,
+            notified: false,
+        }
+    }
+
+    pub fn state(&self) -> JobState {
+        let mut running = self.processes.iter().filter(|p| p.status.is_none());
+
+        match running.clone().next() {
+            None => JobState::Done,
+            Some(_) if running.any(|p| p.stopped) => JobState::Stopped,
+            Some(_) => JobState::Running,
+        }
+    }
+
+    /// The exit status of the job, that of its last process.
+    pub fn status(&self) -> Option<ExitStatus> {
+        self.processes.last()?.status
+    }
+
+    pub fn last_pid(&self) -> pid_t {
+        self.processes.last().map_or(0, |process| process.pid)
+    }
+
+    /// Records a status reported by `waitpid`. Returns false when `pid` is
+    /// not part of this job.
+    pub fn update(&mut self, pid: pid_t, status: i32) -> bool {
+        let previous_state = self.state();
+        let Some(process) = self.processes.iter_mut().find(|p| p.pid == pid) else {
+            return false;
+        };
+
+        if libc::WIFSTOPPED(status) {
+            process.stopped = true;
+        } else if libc::WIFCONTINUED(status) {
+            process.stopped = false;
+        } else {
+            process.status = Some(ExitStatus::from_raw(status));
+        }
+
+        if self.state() != previous_state {
+            self.notified = false;
+        }
+        true
+    }
+
+    /// Sends `signal` to every process of the job.
+    pub fn kill(&self, signal: i32) -> io::Result<()> {
+        let result = if self.pgid > 0 {
+            unsafe { libc::kill(-self.pgid, signal) }
+        } else {
+            let mut result = 0;
+            for process in self.processes.iter().filter(|p| p.status.is_none()) {
+                result |= unsafe { libc::kill(process.pid, signal) };
+            }
+            result
+        };
+
+        if result == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Marks the job as running again after a SIGCONT.
+    pub fn set_running(&mut self) {
+        for process in &mut self.processes {
+            process.stopped = false;
+        }
+        self.notified = false;
+    }
+
+    /// The state column of `jobs`: "Running", "Stopped", "Done",
+    /// "Exit 2", "Terminated"...
+    pub fn describe_state(&self) -> String {
+        match self.state() {
+            JobState::Running => "Running".to_string(),
+            JobState::Stopped => "Stopped".to_string(),
+            JobState::Done => match self.status() {
+                Some(status) => match status.signal() {
+                    Some(signal) => exec::signal_description(signal),
+                    None if status.code() == Some(0) => "Done".to_string(),
+                    None => format!("Exit {}", status.code().unwrap_or(1)),
+                },
+                None => "Done".to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Jobs {
+    jobs: Vec<Job>,
+    /// Job ids from the least to the most recently used: the last one is
+    /// the current job (`%+`), the one before it the previous job (`%-`).
+    recent: Vec<usize>,
+}
+
+impl Jobs {
+    /// Adds a job, keeping its number if it already had one, and makes it
+    /// the current job. Returns its number.
+    pub fn insert(&mut self, mut job: Job) -> usize {
+        if job.id == 0 {
+            job.id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        }
+        let id = job.id;
+
+        let position = self.jobs.partition_point(|other| other.id < id);
+        self.jobs.insert(position, job);
+        self.make_current(id);
+        id
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        let position = self.jobs.iter().position(|job| job.id == id)?;
+        self.recent.retain(|&other| other != id);
+        Some(self.jobs.remove(position))
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.jobs.iter()
+    }
+
+    pub fn make_current(&mut self, id: usize) {
+        self.recent.retain(|&other| other != id);
+        self.recent.push(id);
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.recent.last().copied()
+    }
+
+    pub fn previous(&self) -> Option<usize> {
+        self.recent.iter().rev().nth(1).copied()
+    }
+
+    /// The `+` or `-` shown next to the current and previous jobs.
+    pub fn mark(&self, id: usize) -> char {
+        if self.current() == Some(id) {
+            '+'
+        } else if self.previous() == Some(id) {
+            '-'
+        } else {
+            ' '
+        }
+    }
+
+    /// Formats a job as `jobs` lists it: `[1]+  Running    sleep 10 &`.
+    pub fn format(&self, job: &Job) -> String {
+        let state = job.describe_state();
+        let suffix = if job.state() == JobState::Running {
+            " &"
+        } else {
+            ""
+        };
+        format!(
+            "[{}]{}  {:<24}{}{}",
+            job.id,
+            self.mark(job.id),
+            state,
+            job.command,
+            suffix
+        )
+    }
+
+    /// Resolves a job spec: `%N`, `%+` or `%%`, `%-`, `%name` for a
+    /// command starting with name, or `%?text` for one containing text.
+    pub fn find(&self, spec: &str) -> Result<usize, String> {
+        let no_such_job = || format!("{}: no such job", spec);
+
+        let Some(spec_body) = spec.strip_prefix('%') else {
+            return match spec.parse::<usize>() {
+                Ok(id) if self.get(id).is_some() => Ok(id),
+                _ => Err(no_such_job()),
+            };
+        };
+
+        let found = match spec_body {
+            "" | "+" | "%" => self.current(),
+            "-" => self.previous(),
+            _ if spec_body.chars().all(|c| c.is_ascii_digit()) => spec_body
+                .parse()
+                .ok()
+                .filter(|&id| self.get(id).is_some()),
+            _ => {
+                let matching: Vec<usize> = match spec_body.strip_prefix('?') {
+                    Some(text) => self
+                        .jobs
+                        .iter()
+                        .filter(|job| job.command.contains(text))
+                        .map(|job| job.id)
+                        .collect(),
+                    None => self
+                        .jobs
+                        .iter()
+                        .filter(|job| job.command.starts_with(spec_body))
+                        .map(|job| job.id)
+                        .collect(),
+                };
+                if matching.len() > 1 {
+                    return Err(format!("{}: ambiguous job spec", spec));
+                }
+                matching.first().copied()
+            }
+        };
+
+        found.ok_or_else(no_such_job)
+    }
+
+    /// The job a process belongs to.
+    pub fn find_pid(&self, pid: pid_t) -> Option<usize> {
+        self.jobs
+            .iter()
+            .find(|job| job.processes.iter().any(|process| process.pid == pid))
+            .map(|job| job.id)
+    }
+
+    /// Collects the state changes of every child without blocking.
+    pub fn reap(&mut self) {
+        while let Ok(Some((pid, status))) =
+            wait(-1, libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED)
+        {
+            if let Some(job) = self.jobs.iter_mut().find(|job| job.processes.iter().any(|p| p.pid == pid)) {
+                job.update(pid, status);
+            }
+        }
+    }
+}
+
+/// `waitpid`. `None` when `WNOHANG` is given and no child has changed
+/// state.
+pub fn wait(pid: pid_t, options: i32) -> io::Result<Option<(pid_t, i32)>> {
+    let mut status = 0;
+
+    match unsafe { libc::waitpid(pid, &mut status, options) } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(None),
+        pid => Ok(Some((pid, status))),
+    }
+}
+
+/// Waits until every process of `job` has terminated, or one of them has
+/// stopped. When `interruptible`, Ctrl+C stops the wait with an
+/// `Interrupted` error.
+pub fn wait_for(job: &mut Job, interruptible: bool) -> io::Result<()> {
+    while job.state() == JobState::Running {
+        let Some(process) = job
+            .processes
+            .iter()
+            .find(|process| process.status.is_none() && !process.stopped)
+        else {
+            break;
+        };
+
+        match wait(process.pid, libc::WUNTRACED) {
+            Ok(Some((pid, status))) => {
+                job.update(pid, status);
+            }
+            Ok(None) => {}
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {
+                if interruptible && signals::interrupted() {
+                    return Err(err);
+                }
+            }
+            Err(err) if err.raw_os_error() == Some(libc::ECHILD) => {
+                // Already collected elsewhere: nothing more to learn.
+                let pid = process.pid;
+                job.update(pid, 0);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes `pgid` the foreground process group of the terminal on fd 0.
+pub fn give_terminal(pgid: pid_t) {
+    unsafe { libc::tcsetpgrp(0, pgid) };
+}
+
+pub fn terminal_modes() -> Option<libc::termios> {
+    let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+    if unsafe { libc::tcgetattr(0, &mut termios) } == -1 {
+        return None;
+    }
+    Some(termios)
+}
+
+pub fn set_terminal_modes(termios: &libc::termios) {
+    unsafe { libc::tcsetattr(0, libc::TCSADRAIN, termios) };
+}
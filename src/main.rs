@@ -1,11 +1,73 @@
 mod commands;
+mod completion;
+mod editor;
 mod error;
+mod exec;
+mod expand;
+mod history;
+mod jobs;
 mod shell;
+mod signals;
 mod parser;
+mod options;
+mod redirect;
 mod color;
 mod utils;
+mod variables;
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process,
+};
+
+const USAGE: &str = "usage: 0shell [--norc] [--rcfile path] [-c commands [name [arg ...]] | script [arg ...]]";
 
 fn main() {
-    let mut shell = shell::Shell::new();
-    shell.run_loop();
+    let mut norc = false;
+    let mut rcfile = None;
+    let mut commands = None;
+
+    let mut args = env::args().skip(1).peekable();
+    while let Some(arg) = args.next_if(|arg| arg.starts_with('-')) {
+        match arg.as_str() {
+            "--norc" => norc = true,
+            "--rcfile" | "-c" => {
+                let Some(value) = args.next() else {
+                    eprintln!("0shell: {}: option requires an argument", arg);
+                    eprintln!("{}", USAGE);
+                    process::exit(2);
+                };
+                if arg == "-c" {
+                    commands = Some(value);
+                    break;
+                }
+                rcfile = Some(PathBuf::from(value));
+            }
+            "--" => break,
+            _ => {
+                eprintln!("0shell: {}: invalid option", arg);
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        }
+    }
+
+    if let Some(commands) = commands {
+        let name = args.next();
+        let mut shell = shell::Shell::new(false);
+        process::exit(shell.run_string(&commands, name, args.collect()));
+    }
+
+    if let Some(script) = args.next() {
+        let mut shell = shell::Shell::new(false);
+        process::exit(shell.run_script(Path::new(&script), args.collect()));
+    }
+
+    let mut shell = shell::Shell::new(true);
+    if !norc {
+        shell.load_rc(rcfile.as_deref());
+    }
+    let status = shell.run_loop();
+    process::exit(status);
 }
@@ -0,0 +1,32 @@
+/// Shell options, changed with `set -o name` and `set +o name`.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// A glob pattern matching no file is an error instead of being kept
+    /// as is, like zsh's `nomatch`.
+    pub nomatch: bool,
+    /// Ctrl+R matches history entries containing the typed characters in
+    /// order, not necessarily next to each other.
+    pub fuzzysearch: bool,
+}
+
+impl Options {
+    pub const NAMES: &[&str] = &["fuzzysearch", "nomatch"];
+
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "fuzzysearch" => Some(self.fuzzysearch),
+            "nomatch" => Some(self.nomatch),
+            _ => None,
+        }
+    }
+
+    /// Sets an option, returning false when there is no such option.
+    pub fn set(&mut self, name: &str, value: bool) -> bool {
+        match name {
+            "fuzzysearch" => self.fuzzysearch = value,
+            "nomatch" => self.nomatch = value,
+            _ => return false,
+        }
+        true
+    }
+}
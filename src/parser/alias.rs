@@ -0,0 +1,152 @@
+use std::{collections::BTreeMap, iter, mem, rc::Rc};
+
+use super::{CompoundCommand, List, SimpleCommand, Stage, WordPart, parse_command};
+use crate::error::ShellError;
+
+/// Replaces the aliases in command position throughout `list` with their
+/// value, as read from the command line: the unquoted command name of a
+/// simple command, and the word after an alias whose value ends with a
+/// blank. The text of an alias is read again, so aliases it starts with are
+/// expanded in turn, except those already being expanded.
+pub fn expand_aliases(
+    list: &mut List,
+    aliases: &BTreeMap<String, String>,
+) -> Result<(), ShellError> {
+    if aliases.is_empty() {
+        return Ok(());
+    }
+    expand_list(list, aliases, &mut Vec::new())
+}
+
+fn expand_list(
+    list: &mut List,
+    aliases: &BTreeMap<String, String>,
+    seen: &mut Vec<String>,
+) -> Result<(), ShellError> {
+    for and_or in &mut list.items {
+        let pipelines = iter::once(&mut and_or.first)
+            .chain(and_or.rest.iter_mut().map(|(_, pipeline)| pipeline));
+        for pipeline in pipelines {
+            for stage in &mut pipeline.commands {
+                expand_stage(stage, aliases, seen)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn expand_stage(
+    stage: &mut Stage,
+    aliases: &BTreeMap<String, String>,
+    seen: &mut Vec<String>,
+) -> Result<(), ShellError> {
+    match stage {
+        Stage::Simple(command) => {
+            if let Some(expanded) = expand_simple(command, aliases, seen)? {
+                *stage = expanded;
+            }
+            Ok(())
+        }
+        Stage::Compound(command, _) => expand_compound(command, aliases, seen),
+        Stage::Function(function) => {
+            expand_compound(&mut Rc::make_mut(function).body, aliases, seen)
+        }
+    }
+}
+
+fn expand_compound(
+    command: &mut CompoundCommand,
+    aliases: &BTreeMap<String, String>,
+    seen: &mut Vec<String>,
+) -> Result<(), ShellError> {
+    match command {
+        CompoundCommand::If { branches, otherwise } => {
+            for (condition, body) in branches {
+                expand_list(condition, aliases, seen)?;
+                expand_list(body, aliases, seen)?;
+            }
+            if let Some(otherwise) = otherwise {
+                expand_list(otherwise, aliases, seen)?;
+            }
+            Ok(())
+        }
+        CompoundCommand::While { condition, body, .. } => {
+            expand_list(condition, aliases, seen)?;
+            expand_list(body, aliases, seen)
+        }
+        CompoundCommand::For { body, .. } | CompoundCommand::ArithmeticFor { body, .. } => {
+            expand_list(body, aliases, seen)
+        }
+        CompoundCommand::Case { items, .. } => {
+            for item in items {
+                expand_list(&mut item.body, aliases, seen)?;
+            }
+            Ok(())
+        }
+        CompoundCommand::Group(list) => expand_list(list, aliases, seen),
+        CompoundCommand::Arithmetic(_) => Ok(()),
+    }
+}
+
+/// The command `command` turns into once its leading aliases are replaced,
+/// or `None` when it does not start with one.
+fn expand_simple(
+    command: &SimpleCommand,
+    aliases: &BTreeMap<String, String>,
+    seen: &mut Vec<String>,
+) -> Result<Option<Stage>, ShellError> {
+    let mut source = String::new();
+    for assignment in &command.assignments {
+        source.push_str(&format!("{}={} ", assignment.name, assignment.value));
+    }
+
+    let mut expanded = Vec::new();
+    let mut words = command.words.iter().peekable();
+    while let Some(word) = words.peek()
+        && let [WordPart::Literal(name)] = word.parts.as_slice()
+        && !seen.contains(name)
+        && let Some(value) = aliases.get(name)
+    {
+        words.next();
+        source.push_str(value);
+        source.push(' ');
+        expanded.push(name.clone());
+
+        // A blank at the end makes the next word an alias too.
+        if !value.ends_with([' ', '\t']) {
+            break;
+        }
+    }
+
+    if expanded.is_empty() {
+        return Ok(None);
+    }
+
+    let rest = SimpleCommand {
+        assignments: Vec::new(),
+        words: words.cloned().collect(),
+        redirects: command.redirects.clone(),
+    };
+    source.push_str(&rest.to_string());
+
+    let mut list = parse_command(&source)?;
+    let depth = seen.len();
+    seen.extend(expanded);
+    let result = expand_list(&mut list, aliases, seen);
+    seen.truncate(depth);
+    result?;
+
+    // A single command takes the place of the one the alias started;
+    // anything more runs as a group.
+    if let [and_or] = list.items.as_mut_slice()
+        && !and_or.background
+        && and_or.rest.is_empty()
+        && let [stage] = and_or.first.commands.as_mut_slice()
+    {
+        return Ok(Some(mem::replace(stage, Stage::Simple(SimpleCommand::default()))));
+    }
+    if list.is_empty() {
+        return Ok(Some(Stage::Simple(SimpleCommand::default())));
+    }
+    Ok(Some(Stage::Compound(CompoundCommand::Group(list), Vec::new())))
+}
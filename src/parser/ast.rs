@@ -0,0 +1,451 @@
+use std::{fmt, rc::Rc};
+
+/// One piece of a word, as written on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WordPart {
+    /// Unquoted text.
+    Literal(String),
+    /// Text from quotes or backslash escapes, taken as is.
+    Quoted(String),
+    /// A parameter expansion such as `$HOME` or `${name:-default}`.
+    Param(Param),
+    /// A command substitution, `$(command)` or `` `command` ``, replaced by
+    /// the output of `source`.
+    Command { source: String, quoted: bool },
+    /// An arithmetic expansion, `$((expression))`, replaced by the value of
+    /// `expression` once expanded.
+    Arithmetic { expression: Word, quoted: bool },
+    /// A `${...}` we could not make sense of, reported when expanded.
+    BadSubstitution(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub op: ParamOp,
+    /// Inside double quotes: the result is not split into fields.
+    pub quoted: bool,
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = &self.name;
+        let colon = |colon: &bool| if *colon { ":" } else { "" };
+        let double = |longest: &bool, op: &str| if *longest { op.repeat(2) } else { op.to_string() };
+
+        match &self.op {
+            ParamOp::Value => write!(f, "${{{}}}", name),
+            ParamOp::Length => write!(f, "${{#{}}}", name),
+            ParamOp::Default(word, c) => write!(f, "${{{}{}-{}}}", name, colon(c), word),
+            ParamOp::Assign(word, c) => write!(f, "${{{}{}={}}}", name, colon(c), word),
+            ParamOp::Alternative(word, c) => write!(f, "${{{}{}+{}}}", name, colon(c), word),
+            ParamOp::Error(word, c) => write!(f, "${{{}{}?{}}}", name, colon(c), word),
+            ParamOp::TrimPrefix(word, longest) => {
+                write!(f, "${{{}{}{}}}", name, double(longest, "#"), word)
+            }
+            ParamOp::TrimSuffix(word, longest) => {
+                write!(f, "${{{}{}{}}}", name, double(longest, "%"), word)
+            }
+        }
+    }
+}
+
+/// What to do with a parameter's value. The `bool` of the default-style
+/// operators is true for the `:` forms, which also treat an empty value as
+/// unset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamOp {
+    /// `$name`, `${name}`
+    Value,
+    /// `${#name}`
+    Length,
+    /// `${name:-word}`
+    Default(Word, bool),
+    /// `${name:=word}`
+    Assign(Word, bool),
+    /// `${name:+word}`
+    Alternative(Word, bool),
+    /// `${name:?word}`
+    Error(Word, bool),
+    /// `${name#pattern}`, or `##` for the longest match
+    TrimPrefix(Word, bool),
+    /// `${name%pattern}`, or `%%` for the longest match
+    TrimSuffix(Word, bool),
+}
+
+/// A word before expansion.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+impl fmt::Display for Word {
+    /// The word as it can be typed back, its quoted parts in double quotes.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut in_quotes = false;
+
+        for part in &self.parts {
+            let quoted = match part {
+                WordPart::Literal(_) | WordPart::BadSubstitution(_) => false,
+                WordPart::Quoted(_) => true,
+                WordPart::Param(param) => param.quoted,
+                WordPart::Command { quoted, .. } | WordPart::Arithmetic { quoted, .. } => *quoted,
+            };
+            if quoted != in_quotes {
+                write!(f, "\"")?;
+                in_quotes = quoted;
+            }
+
+            match part {
+                WordPart::Literal(text) | WordPart::BadSubstitution(text) => write!(f, "{}", text)?,
+                WordPart::Quoted(text) => {
+                    for ch in text.chars() {
+                        if matches!(ch, '\\' | '$' | '"' | '`') {
+                            write!(f, "\\")?;
+                        }
+                        write!(f, "{}", ch)?;
+                    }
+                }
+                WordPart::Param(param) => write!(f, "{}", param)?,
+                WordPart::Command { source, .. } => write!(f, "$({})", source)?,
+                WordPart::Arithmetic { expression, .. } => write!(f, "$(({}))", expression)?,
+            }
+        }
+
+        if in_quotes {
+            write!(f, "\"")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RedirectOp {
+    /// `<`
+    Read,
+    /// `>`
+    Write,
+    /// `>>`
+    Append,
+    /// `<&`
+    DupRead,
+    /// `>&`
+    DupWrite,
+    /// `&>`, both stdout and stderr
+    WriteAll,
+    /// `&>>`
+    AppendAll,
+}
+
+impl fmt::Display for RedirectOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            RedirectOp::Read => "<",
+            RedirectOp::Write => ">",
+            RedirectOp::Append => ">>",
+            RedirectOp::DupRead => "<&",
+            RedirectOp::DupWrite => ">&",
+            RedirectOp::WriteAll => "&>",
+            RedirectOp::AppendAll => "&>>",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub fd: i32,
+    pub op: RedirectOp,
+    pub target: Word,
+}
+
+impl fmt::Display for Redirect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let default_fd = match self.op {
+            RedirectOp::Read | RedirectOp::DupRead => 0,
+            RedirectOp::WriteAll | RedirectOp::AppendAll => self.fd,
+            _ => 1,
+        };
+        if self.fd != default_fd {
+            write!(f, "{}", self.fd)?;
+        }
+        write!(f, "{}{}", self.op, self.target)
+    }
+}
+
+/// A `NAME=value` word in front of a command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub name: String,
+    pub value: Word,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SimpleCommand {
+    /// Variables set for this command only, or in the shell itself when
+    /// there is no command.
+    pub assignments: Vec<Assignment>,
+    /// The command name followed by its arguments; empty for a command made
+    /// of redirections only, like `> file`.
+    pub words: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+}
+
+impl fmt::Display for SimpleCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let assignments = self
+            .assignments
+            .iter()
+            .map(|assignment| format!("{}={}", assignment.name, assignment.value));
+        let words = self.words.iter().map(Word::to_string);
+        let redirects = self.redirects.iter().map(Redirect::to_string);
+
+        let parts: Vec<String> = assignments.chain(words).chain(redirects).collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// A command of the shell language, with the redirections that apply to
+/// the whole of it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompoundCommand {
+    /// `if list; then list; [elif list; then list;]... [else list;] fi`
+    If {
+        branches: Vec<(List, List)>,
+        otherwise: Option<List>,
+    },
+    /// `while list; do list; done`, or `until` when `until` is set.
+    While {
+        condition: List,
+        body: List,
+        until: bool,
+    },
+    /// `for name [in word...]; do list; done`. Without `in`, the loop goes
+    /// over the positional parameters.
+    For {
+        name: String,
+        words: Option<Vec<Word>>,
+        body: List,
+    },
+    /// `for ((init; condition; step)); do list; done`
+    ArithmeticFor {
+        init: Word,
+        condition: Word,
+        step: Word,
+        body: List,
+    },
+    /// `case word in [(]pattern[|pattern]...) list ;; ... esac`
+    Case { word: Word, items: Vec<CaseItem> },
+    /// `{ list; }`
+    Group(List),
+    /// `((expression))`, which succeeds when `expression` is not 0.
+    Arithmetic(Word),
+}
+
+/// What comes after the commands of a `case` item that matched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaseTerminator {
+    /// `;;`: the `case` is done.
+    Break,
+    /// `;&`: the commands of the next item run too, whatever its patterns.
+    FallThrough,
+    /// `;;&`: the patterns of the next items are tried as well.
+    Continue,
+}
+
+impl fmt::Display for CaseTerminator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            CaseTerminator::Break => ";;",
+            CaseTerminator::FallThrough => ";&",
+            CaseTerminator::Continue => ";;&",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// `pattern|pattern) list ;;` in a `case`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseItem {
+    pub patterns: Vec<Word>,
+    pub body: List,
+    pub terminator: CaseTerminator,
+}
+
+impl fmt::Display for CompoundCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompoundCommand::If {
+                branches,
+                otherwise,
+            } => {
+                for (i, (condition, body)) in branches.iter().enumerate() {
+                    let keyword = if i == 0 { "if" } else { "elif" };
+                    write!(f, "{} {} then {} ", keyword, condition, body)?;
+                }
+                if let Some(otherwise) = otherwise {
+                    write!(f, "else {} ", otherwise)?;
+                }
+                write!(f, "fi")
+            }
+            CompoundCommand::While {
+                condition,
+                body,
+                until,
+            } => {
+                let keyword = if *until { "until" } else { "while" };
+                write!(f, "{} {} do {} done", keyword, condition, body)
+            }
+            CompoundCommand::For { name, words, body } => {
+                write!(f, "for {}", name)?;
+                if let Some(words) = words {
+                    write!(f, " in")?;
+                    for word in words {
+                        write!(f, " {}", word)?;
+                    }
+                }
+                write!(f, "; do {} done", body)
+            }
+            CompoundCommand::ArithmeticFor {
+                init,
+                condition,
+                step,
+                body,
+            } => write!(
+                f,
+                "for (({}; {}; {})); do {} done",
+                init, condition, step, body
+            ),
+            CompoundCommand::Case { word, items } => {
+                write!(f, "case {} in", word)?;
+                for item in items {
+                    let patterns: Vec<String> = item.patterns.iter().map(Word::to_string).collect();
+                    write!(f, " {})", patterns.join("|"))?;
+                    if !item.body.is_empty() {
+                        write!(f, " {}", item.body)?;
+                    }
+                    write!(f, " {}", item.terminator)?;
+                }
+                write!(f, " esac")
+            }
+            CompoundCommand::Group(list) => write!(f, "{{ {} }}", list),
+            CompoundCommand::Arithmetic(expression) => write!(f, "(({}))", expression),
+        }
+    }
+}
+
+/// `name() compound-command`, or `function name compound-command`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub body: CompoundCommand,
+    /// Applied each time the function runs.
+    pub redirects: Vec<Redirect>,
+}
+
+impl fmt::Display for Function {
+    /// The definition as `declare -f` shows it, which can be run again.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} () {}", self.name, self.body)?;
+        for redirect in &self.redirects {
+            write!(f, " {}", redirect)?;
+        }
+        Ok(())
+    }
+}
+
+/// One command of a pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stage {
+    Simple(SimpleCommand),
+    Compound(CompoundCommand, Vec<Redirect>),
+    /// A function definition, which runs nothing but records the function.
+    Function(Rc<Function>),
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stage::Simple(command) => write!(f, "{}", command),
+            Stage::Compound(command, redirects) => {
+                write!(f, "{}", command)?;
+                for redirect in redirects {
+                    write!(f, " {}", redirect)?;
+                }
+                Ok(())
+            }
+            Stage::Function(function) => write!(f, "{}", function),
+        }
+    }
+}
+
+/// Commands connected with `|`, the output of each one feeding the next.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pipeline {
+    pub commands: Vec<Stage>,
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let commands: Vec<String> = self.commands.iter().map(Stage::to_string).collect();
+        write!(f, "{}", commands.join(" | "))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connector {
+    /// `&&`: run the next pipeline if the previous one succeeded.
+    And,
+    /// `||`: run the next pipeline if the previous one failed.
+    Or,
+}
+
+/// Pipelines chained with `&&` and `||`, evaluated left to right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AndOr {
+    pub first: Pipeline,
+    pub rest: Vec<(Connector, Pipeline)>,
+    /// Followed by `&`: run as a background job.
+    pub background: bool,
+}
+
+impl fmt::Display for AndOr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.first)?;
+        for (connector, pipeline) in &self.rest {
+            let connector = match connector {
+                Connector::And => "&&",
+                Connector::Or => "||",
+            };
+            write!(f, " {} {}", connector, pipeline)?;
+        }
+        Ok(())
+    }
+}
+
+/// A command line: and-or lists separated by `;` or newlines, run one
+/// after the other.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct List {
+    pub items: Vec<AndOr>,
+}
+
+impl List {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl fmt::Display for List {
+    /// The and-or lists on one line, each one ended with `;` or `&`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, and_or) in self.items.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            let terminator = if and_or.background { "&" } else { ";" };
+            write!(f, "{}{}", and_or, terminator)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use crate::error::ShellError;
+
+/// Applies history expansion to a line typed at the prompt, `history`
+/// being the previous lines, oldest first:
+///
+/// - `!!`: the previous command
+/// - `!n`: command number n, `!-n`: the nth command back
+/// - `!prefix`: the last command starting with prefix, `!?text`: the last
+///   one containing text
+/// - `^old^new` at the start of a line: the previous command with the
+///   first occurrence of old replaced by new
+///
+/// Nothing is expanded inside single quotes or after a backslash. Returns
+/// `None` when the line has nothing to expand.
+pub fn expand_history(line: &str, history: &[String]) -> Result<Option<String>, ShellError> {
+    if let Some(rest) = line.strip_prefix('^') {
+        return quick_substitution(rest, history).map(Some);
+    }
+
+    let chars: Vec<char> = line.chars().collect();
+    let mut output = String::new();
+    let mut expanded = false;
+    let mut single_quoted = false;
+    let mut double_quoted = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let ch = chars[i];
+        match ch {
+            '\'' if !double_quoted => single_quoted = !single_quoted,
+            '"' if !single_quoted => double_quoted = !double_quoted,
+            '\\' if !single_quoted && i + 1 < chars.len() => {
+                output.push(ch);
+                output.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '!' if !single_quoted && starts_event(&chars, i) => {
+                let (event, end) = event_designator(&chars, i + 1);
+                let text = find_event(&event, history)
+                    .ok_or_else(|| ShellError::Other(format!("!{}: event not found", event)))?;
+                output.push_str(text);
+                expanded = true;
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+        output.push(ch);
+        i += 1;
+    }
+
+    Ok(expanded.then_some(output))
+}
+
+/// Whether the `!` at `i` starts an event rather than being a plain `!`, as
+/// in `! cmd`, `!=`, `$!` or `[!a-z]`.
+fn starts_event(chars: &[char], i: usize) -> bool {
+    if i > 0 && matches!(chars[i - 1], '$' | '[') {
+        return false;
+    }
+    match chars.get(i + 1) {
+        None => false,
+        Some(next) => !next.is_whitespace() && !matches!(next, '=' | '(' | '"'),
+    }
+}
+
+/// Reads the event after a `!` starting at `start`. Returns it without the
+/// `!`, and the index just past it.
+fn event_designator(chars: &[char], start: usize) -> (String, usize) {
+    match chars[start] {
+        '!' => ("!".to_string(), start + 1),
+        '?' => {
+            let mut end = start + 1;
+            while end < chars.len() && chars[end] != '?' {
+                end += 1;
+            }
+            let event: String = chars[start..end].iter().collect();
+            // The closing `?` is optional at the end of the line.
+            (event, (end + 1).min(chars.len()))
+        }
+        '-' | '0'..='9' => {
+            let mut end = start + 1;
+            while end < chars.len() && chars[end].is_ascii_digit() {
+                end += 1;
+            }
+            (chars[start..end].iter().collect(), end)
+        }
+        _ => {
+            let mut end = start;
+            while end < chars.len()
+                && !chars[end].is_whitespace()
+                && !matches!(chars[end], ';' | '&' | '|' | '<' | '>' | '(' | ')' | '"' | '\'')
+            {
+                end += 1;
+            }
+            (chars[start..end].iter().collect(), end)
+        }
+    }
+}
+
+fn find_event<'a>(event: &str, history: &'a [String]) -> Option<&'a String> {
+    if event == "!" {
+        return history.last();
+    }
+    if let Some(text) = event.strip_prefix('?') {
+        return history.iter().rev().find(|entry| entry.contains(text));
+    }
+    if let Some(back) = event.strip_prefix('-') {
+        let back: usize = back.parse().ok()?;
+        return history.len().checked_sub(back).and_then(|index| history.get(index));
+    }
+    if let Ok(number) = event.parse::<usize>() {
+        return number.checked_sub(1).and_then(|index| history.get(index));
+    }
+    history.iter().rev().find(|entry| entry.starts_with(event))
+}
+
+/// `^old^new^`: the previous command with old replaced by new, the closing
+/// `^` being optional. Whatever follows it is appended.
+fn quick_substitution(rest: &str, history: &[String]) -> Result<String, ShellError> {
+    let (old, replacement) = rest.split_once('^').unwrap_or((rest, ""));
+    let (new, suffix) = replacement.split_once('^').unwrap_or((replacement, ""));
+
+    let previous = history
+        .last()
+        .ok_or_else(|| ShellError::Other("!!: event not found".to_string()))?;
+    if old.is_empty() || !previous.contains(old) {
+        return Err(ShellError::Other(format!("^{}^{}: substitution failed", old, new)));
+    }
+
+    Ok(format!("{}{}", previous.replacen(old, new, 1), suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> Vec<String> {
+        ["ls -l", "echo hello", "cat file"]
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect()
+    }
+
+    fn expand(line: &str) -> Option<String> {
+        expand_history(line, &history()).unwrap()
+    }
+
+    #[test]
+    fn expands_events() {
+        assert_eq!(expand("!!").as_deref(), Some("cat file"));
+        assert_eq!(expand("sudo !!").as_deref(), Some("sudo cat file"));
+        assert_eq!(expand("!1").as_deref(), Some("ls -l"));
+        assert_eq!(expand("!-2").as_deref(), Some("echo hello"));
+        assert_eq!(expand("!ec").as_deref(), Some("echo hello"));
+        assert_eq!(expand("!?fil").as_deref(), Some("cat file"));
+    }
+
+    #[test]
+    fn substitutes_in_the_previous_command() {
+        assert_eq!(expand("^file^other").as_deref(), Some("cat other"));
+        assert_eq!(expand("^cat^less^ -N").as_deref(), Some("less file -N"));
+        assert!(expand_history("^nope^x", &history()).is_err());
+    }
+
+    #[test]
+    fn leaves_quoted_and_plain_text_alone() {
+        assert_eq!(expand("echo hi"), None);
+        assert_eq!(expand("echo '!!'"), None);
+        assert_eq!(expand("echo \\!!"), None);
+        assert_eq!(expand("echo a! b"), None);
+    }
+
+    #[test]
+    fn missing_events_are_errors() {
+        assert!(expand_history("!42", &history()).is_err());
+        assert!(expand_history("!nothing", &history()).is_err());
+        assert!(expand_history("!!", &[]).is_err());
+    }
+}
@@ -0,0 +1,36 @@
+use std::io::BufRead;
+
+use crate::error::ShellError;
+
+/// Where the parser reads commands from, one line at a time: the prompt, a
+/// script file or a `-c` string.
+pub trait LineSource {
+    /// Reads the next line, without its newline. `continuation` is set
+    /// when the line continues an incomplete command. `None` at end of
+    /// input.
+    fn read_line(&mut self, continuation: bool) -> Result<Option<String>, ShellError>;
+}
+
+/// Lines from a reader, such as a file or the bytes of a string.
+pub struct ReaderSource<R> {
+    reader: R,
+}
+
+impl<R: BufRead> ReaderSource<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+}
+
+impl<R: BufRead> LineSource for ReaderSource<R> {
+    fn read_line(&mut self, _continuation: bool) -> Result<Option<String>, ShellError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+}
@@ -0,0 +1,705 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::error::ShellError;
+
+use super::ast::{CaseTerminator, Param, ParamOp, RedirectOp, Word, WordPart};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Word(Word),
+    Pipe,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `;`
+    Semicolon,
+    /// `;;`, `;&` or `;;&`, ending an item of a `case`.
+    CaseEnd(CaseTerminator),
+    /// `(`
+    OpenParen,
+    /// `)`
+    CloseParen,
+    /// `&`, which runs what comes before it in the background.
+    Ampersand,
+    Newline,
+    /// A redirection operator, with the descriptor written in front of it
+    /// (`2>`) when there is one.
+    Redirect(Option<i32>, RedirectOp),
+    /// `((expression))`, holding the expression.
+    Arithmetic(String),
+}
+
+#[derive(Default)]
+struct WordBuffer {
+    word: Word,
+    started: bool,
+}
+
+impl WordBuffer {
+    fn push(&mut self, ch: char) {
+        self.started = true;
+        if let Some(WordPart::Literal(text)) = self.word.parts.last_mut() {
+            text.push(ch);
+        } else {
+            self.word.parts.push(WordPart::Literal(ch.to_string()));
+        }
+    }
+
+    fn push_quoted(&mut self, ch: char) {
+        self.started = true;
+        if let Some(WordPart::Quoted(text)) = self.word.parts.last_mut() {
+            text.push(ch);
+        } else {
+            self.word.parts.push(WordPart::Quoted(ch.to_string()));
+        }
+    }
+
+    fn push_part(&mut self, part: WordPart) {
+        self.started = true;
+        self.word.parts.push(part);
+    }
+
+    fn flush(&mut self, tokens: &mut Vec<Token>) {
+        if self.started {
+            tokens.push(Token::Word(std::mem::take(&mut self.word)));
+        }
+        self.started = false;
+    }
+
+    /// Takes the word as a file descriptor number when it is made of
+    /// unquoted digits only, as in `2>` or `10<`.
+    fn take_io_number(&mut self) -> Option<i32> {
+        let [WordPart::Literal(text)] = self.word.parts.as_slice() else {
+            return None;
+        };
+        if !text.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let fd = text.parse().ok()?;
+        self.word.parts.clear();
+        self.started = false;
+        Some(fd)
+    }
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    word: WordBuffer,
+}
+
+pub fn tokenize(input: &str) -> Result<Vec<Token>, ShellError> {
+    let mut lexer = Lexer::new(input);
+    let mut result = Vec::new();
+
+    while let Some(ch) = lexer.chars.next() {
+        match ch {
+            ' ' | '\t' => {
+                lexer.word.flush(&mut result);
+            }
+            '\n' => {
+                lexer.word.flush(&mut result);
+                result.push(Token::Newline);
+            }
+            // A comment runs to the end of the line, `#` starting a word only.
+            '#' if !lexer.word.started => {
+                while lexer.chars.next_if(|&ch| ch != '\n').is_some() {}
+            }
+            '(' if !lexer.word.started && lexer.chars.next_if_eq(&'(').is_some() => {
+                result.push(Token::Arithmetic(lexer.arithmetic()?));
+            }
+            '(' | ')' => {
+                lexer.word.flush(&mut result);
+                result.push(if ch == '(' {
+                    Token::OpenParen
+                } else {
+                    Token::CloseParen
+                });
+            }
+            ';' => {
+                lexer.word.flush(&mut result);
+                let token = if lexer.chars.next_if_eq(&';').is_some() {
+                    if lexer.chars.next_if_eq(&'&').is_some() {
+                        Token::CaseEnd(CaseTerminator::Continue)
+                    } else {
+                        Token::CaseEnd(CaseTerminator::Break)
+                    }
+                } else if lexer.chars.next_if_eq(&'&').is_some() {
+                    Token::CaseEnd(CaseTerminator::FallThrough)
+                } else {
+                    Token::Semicolon
+                };
+                result.push(token);
+            }
+            '|' => {
+                lexer.word.flush(&mut result);
+                if lexer.chars.next_if_eq(&'|').is_some() {
+                    result.push(Token::Or);
+                } else {
+                    result.push(Token::Pipe);
+                }
+            }
+            '&' if lexer.chars.next_if_eq(&'&').is_some() => {
+                lexer.word.flush(&mut result);
+                result.push(Token::And);
+            }
+            '&' if lexer.chars.peek() != Some(&'>') => {
+                lexer.word.flush(&mut result);
+                result.push(Token::Ampersand);
+            }
+            '<' | '>' => {
+                let fd = lexer.word.take_io_number();
+                lexer.word.flush(&mut result);
+                result.push(Token::Redirect(fd, redirect_op(ch, &mut lexer.chars)));
+            }
+            '&' if lexer.chars.peek() == Some(&'>') => {
+                lexer.chars.next();
+                lexer.word.flush(&mut result);
+                let op = if lexer.chars.next_if_eq(&'>').is_some() {
+                    RedirectOp::AppendAll
+                } else {
+                    RedirectOp::WriteAll
+                };
+                result.push(Token::Redirect(None, op));
+            }
+            _ => lexer.word_char(ch)?,
+        }
+    }
+
+    lexer.word.flush(&mut result);
+    Ok(result)
+}
+
+/// Lexes `text` as the inside of a single word: blanks and operators are
+/// ordinary characters. Used for the operand of `${name:-word}` and for
+/// arithmetic expressions.
+pub fn lex_word(text: &str) -> Result<Word, ShellError> {
+    let mut lexer = Lexer::new(text);
+
+    while let Some(ch) = lexer.chars.next() {
+        lexer.word_char(ch)?;
+    }
+
+    Ok(lexer.word.word)
+}
+
+impl<'a> Lexer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            chars: input.chars().peekable(),
+            word: WordBuffer::default(),
+        }
+    }
+
+    fn word_char(&mut self, ch: char) -> Result<(), ShellError> {
+        match ch {
+            '\'' => self.single_quoted(),
+            '"' => self.double_quoted(),
+            '\\' => match self.chars.next() {
+                // A backslash at the end of a line joins the next one.
+                Some('\n') => Ok(()),
+                Some(next_ch) => {
+                    self.word.push_quoted(next_ch);
+                    Ok(())
+                }
+                None => Err(ShellError::Incomplete),
+            },
+            '$' => self.dollar(false),
+            '`' => {
+                let source = self.backquoted()?;
+                self.word.push_part(WordPart::Command {
+                    source,
+                    quoted: false,
+                });
+                Ok(())
+            }
+            _ => {
+                self.word.push(ch);
+                Ok(())
+            }
+        }
+    }
+
+    fn single_quoted(&mut self) -> Result<(), ShellError> {
+        self.word.started = true;
+
+        loop {
+            match self.chars.next() {
+                Some('\'') => return Ok(()),
+                Some(ch) => self.word.push_quoted(ch),
+                None => return Err(ShellError::Incomplete),
+            }
+        }
+    }
+
+    fn double_quoted(&mut self) -> Result<(), ShellError> {
+        self.word.started = true;
+
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(()),
+                Some('\\') => match self.chars.next() {
+                    // These lose their backslash; other escapes such as `\n`
+                    // are kept for the commands (echo) to interpret.
+                    Some(next_ch @ ('\\' | '$' | '"' | '`')) => self.word.push_quoted(next_ch),
+                    Some('\n') => {}
+                    Some(next_ch) => {
+                        self.word.push_quoted('\\');
+                        self.word.push_quoted(next_ch);
+                    }
+                    None => self.word.push_quoted('\\'),
+                },
+                Some('$') => self.dollar(true)?,
+                Some('`') => {
+                    let source = self.backquoted()?;
+                    self.word.push_part(WordPart::Command {
+                        source,
+                        quoted: true,
+                    });
+                }
+                Some(ch) => self.word.push_quoted(ch),
+                None => return Err(ShellError::Incomplete),
+            }
+        }
+    }
+
+    /// Handles what follows a `$`; a `$` that starts no expansion is kept
+    /// as a plain character.
+    fn dollar(&mut self, quoted: bool) -> Result<(), ShellError> {
+        let name = match self.chars.peek() {
+            Some('{') => {
+                self.chars.next();
+                let raw = self.braced()?;
+                self.word.push_part(parse_braced(&raw, quoted));
+                return Ok(());
+            }
+            Some('(') => {
+                self.chars.next();
+                if self.chars.next_if_eq(&'(').is_some() {
+                    let expression = lex_word(&self.arithmetic()?)?;
+                    self.word.push_part(WordPart::Arithmetic { expression, quoted });
+                    return Ok(());
+                }
+                let source = self.parenthesized()?;
+                self.word.push_part(WordPart::Command { source, quoted });
+                return Ok(());
+            }
+            Some(&ch) if is_special_param(ch) || ch.is_ascii_digit() => {
+                self.chars.next();
+                ch.to_string()
+            }
+            Some(&ch) if ch.is_alphabetic() || ch == '_' => {
+                let mut name = String::new();
+                while let Some(ch) = self.chars.next_if(|&c| c.is_alphanumeric() || c == '_') {
+                    name.push(ch);
+                }
+                name
+            }
+            _ => {
+                if quoted {
+                    self.word.push_quoted('$');
+                } else {
+                    self.word.push('$');
+                }
+                return Ok(());
+            }
+        };
+
+        self.word.push_part(WordPart::Param(Param {
+            name,
+            op: ParamOp::Value,
+            quoted,
+        }));
+        Ok(())
+    }
+
+    /// Reads the inside of `${...}` up to the matching brace.
+    fn braced(&mut self) -> Result<String, ShellError> {
+        let mut raw = String::new();
+        let mut depth = 0;
+
+        while let Some(ch) = self.chars.next() {
+            match ch {
+                '}' if depth == 0 => return Ok(raw),
+                '}' => depth -= 1,
+                '$' if self.chars.peek() == Some(&'{') => depth += 1,
+                '\\' => {
+                    raw.push(ch);
+                    match self.chars.next() {
+                        Some(next_ch) => raw.push(next_ch),
+                        None => break,
+                    }
+                    continue;
+                }
+                '\'' | '"' => {
+                    self.copy_quoted(ch, &mut raw)?;
+                    continue;
+                }
+                _ => {}
+            }
+            raw.push(ch);
+        }
+
+        Err(ShellError::Incomplete)
+    }
+
+    /// Reads the command of `$(...)` up to the matching parenthesis.
+    fn parenthesized(&mut self) -> Result<String, ShellError> {
+        let mut source = String::new();
+        let mut depth = 0;
+
+        while let Some(ch) = self.chars.next() {
+            match ch {
+                ')' if depth == 0 => return Ok(source),
+                ')' => depth -= 1,
+                '(' => depth += 1,
+                '\\' => {
+                    source.push(ch);
+                    match self.chars.next() {
+                        Some(next_ch) => source.push(next_ch),
+                        None => break,
+                    }
+                    continue;
+                }
+                '\'' | '"' | '`' => {
+                    self.copy_quoted(ch, &mut source)?;
+                    continue;
+                }
+                _ => {}
+            }
+            source.push(ch);
+        }
+
+        Err(ShellError::Incomplete)
+    }
+
+    /// Reads the expression of `((...))` up to the closing `))`. Parentheses
+    /// inside it must balance.
+    fn arithmetic(&mut self) -> Result<String, ShellError> {
+        let mut expression = String::new();
+        let mut depth = 0;
+
+        while let Some(ch) = self.chars.next() {
+            match ch {
+                ')' if depth == 0 && self.chars.next_if_eq(&')').is_some() => {
+                    return Ok(expression);
+                }
+                ')' => depth -= 1,
+                '(' => depth += 1,
+                _ => {}
+            }
+            expression.push(ch);
+        }
+
+        Err(ShellError::Incomplete)
+    }
+
+    /// Reads the command between backquotes, where a backslash only
+    /// escapes `\\`, `` ` `` and `$`.
+    fn backquoted(&mut self) -> Result<String, ShellError> {
+        let mut source = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some('`') => return Ok(source),
+                Some('\\') => match self.chars.next() {
+                    Some(next_ch @ ('\\' | '`' | '$')) => source.push(next_ch),
+                    Some(next_ch) => {
+                        source.push('\\');
+                        source.push(next_ch);
+                    }
+                    None => source.push('\\'),
+                },
+                Some(ch) => source.push(ch),
+                None => return Err(ShellError::Incomplete),
+            }
+        }
+    }
+
+    /// Copies a quoted string, quotes included, into `raw`, which is lexed
+    /// again later. Backslashes keep escaping the closing quote except
+    /// inside single quotes.
+    fn copy_quoted(&mut self, quote: char, raw: &mut String) -> Result<(), ShellError> {
+        raw.push(quote);
+
+        loop {
+            match self.chars.next() {
+                Some(ch) if ch == quote => break,
+                Some('\\') if quote != '\'' => {
+                    raw.push('\\');
+                    if let Some(next_ch) = self.chars.next() {
+                        raw.push(next_ch);
+                    }
+                }
+                Some(ch) => raw.push(ch),
+                None => return Err(ShellError::Incomplete),
+            }
+        }
+
+        raw.push(quote);
+        Ok(())
+    }
+}
+
+fn is_special_param(ch: char) -> bool {
+    matches!(ch, '?' | '$' | '#' | '@' | '*' | '!' | '-')
+}
+
+/// Turns the inside of `${...}` into a parameter expansion.
+fn parse_braced(raw: &str, quoted: bool) -> WordPart {
+    let bad = || WordPart::BadSubstitution(format!("${{{}}}", raw));
+
+    // `${#name}` is the length of name, but `${#}` is the special `$#`.
+    if let Some(name) = raw.strip_prefix('#')
+        && !name.is_empty()
+    {
+        if !is_name(name) {
+            return bad();
+        }
+        return WordPart::Param(Param {
+            name: name.to_string(),
+            op: ParamOp::Length,
+            quoted,
+        });
+    }
+
+    let name_len = match raw.chars().next() {
+        Some(ch) if is_special_param(ch) => 1,
+        Some(ch) if ch.is_ascii_digit() => raw.chars().take_while(|c| c.is_ascii_digit()).count(),
+        _ => raw
+            .chars()
+            .take_while(|&c| c.is_alphanumeric() || c == '_')
+            .map(char::len_utf8)
+            .sum(),
+    };
+    let (name, rest) = raw.split_at(name_len);
+
+    if name.is_empty() {
+        return bad();
+    }
+
+    let (colon, rest) = match rest.strip_prefix(':') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+
+    if rest.is_empty() && !colon {
+        return WordPart::Param(Param {
+            name: name.to_string(),
+            op: ParamOp::Value,
+            quoted,
+        });
+    }
+
+    let (operator, operand) = match rest.get(..2) {
+        Some("##" | "%%") if !colon => rest.split_at(2),
+        _ => rest.split_at(rest.chars().next().map_or(0, char::len_utf8)),
+    };
+    let Ok(operand) = lex_word(operand) else {
+        return bad();
+    };
+
+    let op = match operator {
+        "-" => ParamOp::Default(operand, colon),
+        "=" => ParamOp::Assign(operand, colon),
+        "+" => ParamOp::Alternative(operand, colon),
+        "?" => ParamOp::Error(operand, colon),
+        "#" | "##" if !colon => ParamOp::TrimPrefix(operand, operator == "##"),
+        "%" | "%%" if !colon => ParamOp::TrimSuffix(operand, operator == "%%"),
+        _ => return bad(),
+    };
+
+    WordPart::Param(Param {
+        name: name.to_string(),
+        op,
+        quoted,
+    })
+}
+
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn redirect_op(first: char, chars: &mut Peekable<Chars>) -> RedirectOp {
+    match first {
+        '<' if chars.next_if_eq(&'&').is_some() => RedirectOp::DupRead,
+        '<' => RedirectOp::Read,
+        _ if chars.next_if_eq(&'>').is_some() => RedirectOp::Append,
+        _ if chars.next_if_eq(&'&').is_some() => RedirectOp::DupWrite,
+        _ => RedirectOp::Write,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str) -> Token {
+        Token::Word(Word {
+            parts: vec![WordPart::Literal(text.to_string())],
+        })
+    }
+
+    #[test]
+    fn splits_words_and_operators() {
+        assert_eq!(
+            tokenize("a | b && c || d; e\nf").unwrap(),
+            vec![
+                word("a"),
+                Token::Pipe,
+                word("b"),
+                Token::And,
+                word("c"),
+                Token::Or,
+                word("d"),
+                Token::Semicolon,
+                word("e"),
+                Token::Newline,
+                word("f"),
+            ]
+        );
+        assert_eq!(
+            tokenize("a & b&").unwrap(),
+            vec![word("a"), Token::Ampersand, word("b"), Token::Ampersand]
+        );
+    }
+
+    #[test]
+    fn reads_redirections_with_their_descriptor() {
+        assert_eq!(
+            tokenize("cmd 2>err <in >>out &>all 3").unwrap(),
+            vec![
+                word("cmd"),
+                Token::Redirect(Some(2), RedirectOp::Write),
+                word("err"),
+                Token::Redirect(None, RedirectOp::Read),
+                word("in"),
+                Token::Redirect(None, RedirectOp::Append),
+                word("out"),
+                Token::Redirect(None, RedirectOp::WriteAll),
+                word("all"),
+                word("3"),
+            ]
+        );
+        assert_eq!(
+            tokenize("2>&1").unwrap(),
+            vec![Token::Redirect(Some(2), RedirectOp::DupWrite), word("1")]
+        );
+    }
+
+    #[test]
+    fn reads_parameter_expansions() {
+        let tokens = tokenize(r#"$HOME "${x:-y}""#).unwrap();
+        let parts: Vec<&WordPart> = tokens
+            .iter()
+            .map(|token| match token {
+                Token::Word(word) => &word.parts[0],
+                _ => panic!("not a word: {:?}", token),
+            })
+            .collect();
+
+        assert_eq!(
+            parts[0],
+            &WordPart::Param(Param {
+                name: "HOME".to_string(),
+                op: ParamOp::Value,
+                quoted: false,
+            })
+        );
+        assert!(matches!(
+            parts[1],
+            WordPart::Param(Param {
+                name,
+                op: ParamOp::Default(_, true),
+                quoted: true,
+            }) if name == "x"
+        ));
+    }
+
+    #[test]
+    fn reads_command_substitutions() {
+        let command = |source: &str, quoted| {
+            Token::Word(Word {
+                parts: vec![WordPart::Command {
+                    source: source.to_string(),
+                    quoted,
+                }],
+            })
+        };
+        assert_eq!(
+            tokenize(r#"$(cmd arg) "`cmd`""#).unwrap(),
+            vec![command("cmd arg", false), command("cmd", true)]
+        );
+    }
+
+    #[test]
+    fn skips_comments() {
+        assert_eq!(tokenize("echo a # note").unwrap(), vec![word("echo"), word("a")]);
+        assert_eq!(tokenize("echo a#b").unwrap(), vec![word("echo"), word("a#b")]);
+        assert_eq!(tokenize("# note\necho").unwrap(), vec![Token::Newline, word("echo")]);
+    }
+
+    #[test]
+    fn keeps_quoted_text_as_is() {
+        assert_eq!(
+            tokenize(r#"'a b'"c $"d\ e"#).unwrap(),
+            vec![Token::Word(Word {
+                parts: vec![
+                    WordPart::Quoted("a bc $".to_string()),
+                    WordPart::Literal("d".to_string()),
+                    WordPart::Quoted(" ".to_string()),
+                    WordPart::Literal("e".to_string()),
+                ],
+            })]
+        );
+        assert!(matches!(tokenize("echo 'open"), Err(ShellError::Incomplete)));
+        assert!(matches!(tokenize("echo \"open"), Err(ShellError::Incomplete)));
+    }
+
+    #[test]
+    fn reads_case_terminators_and_parentheses() {
+        assert_eq!(
+            tokenize("(a) b;; c;& d;;&").unwrap(),
+            vec![
+                Token::OpenParen,
+                word("a"),
+                Token::CloseParen,
+                word("b"),
+                Token::CaseEnd(CaseTerminator::Break),
+                word("c"),
+                Token::CaseEnd(CaseTerminator::FallThrough),
+                word("d"),
+                Token::CaseEnd(CaseTerminator::Continue),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_arithmetic() {
+        assert_eq!(
+            tokenize("((i++))").unwrap(),
+            vec![Token::Arithmetic("i++".to_string())]
+        );
+
+        let tokens = tokenize(r#"$((1 + 2)) "$((x * 2))""#).unwrap();
+        let expressions: Vec<(String, bool)> = tokens
+            .iter()
+            .map(|token| match token {
+                Token::Word(Word { parts }) => match parts.as_slice() {
+                    [WordPart::Arithmetic { expression, quoted }] => {
+                        (expression.to_string(), *quoted)
+                    }
+                    _ => panic!("not an arithmetic expansion: {:?}", parts),
+                },
+                _ => panic!("not a word: {:?}", token),
+            })
+            .collect();
+        assert_eq!(
+            expressions,
+            [("1 + 2".to_string(), false), ("x * 2".to_string(), true)]
+        );
+    }
+}
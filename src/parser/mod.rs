@@ -0,0 +1,936 @@
+use crate::{
+    error::ShellError,
+    variables::{is_valid_name, split_assignment},
+};
+use std::{iter::Peekable, rc::Rc, vec};
+
+pub use ast::{
+    AndOr, Assignment, CaseItem, CaseTerminator, CompoundCommand, Connector, Function, List, Param,
+    ParamOp, Pipeline, Redirect, RedirectOp, SimpleCommand, Stage, Word, WordPart,
+};
+pub use alias::expand_aliases;
+pub use history::expand_history;
+pub use input::{LineSource, ReaderSource};
+use lexer::{Token, lex_word, tokenize};
+
+mod alias;
+mod ast;
+mod history;
+mod input;
+mod lexer;
+
+/// What a piece of input parses to.
+pub enum Parsed {
+    Complete(List),
+    /// The input stops in the middle of a command: the next line may
+    /// complete it.
+    Incomplete,
+}
+
+pub fn parse(input: &str) -> Result<Parsed, ShellError> {
+    let input = input.trim();
+
+    if input.is_empty() {
+        return Ok(Parsed::Complete(List::default()));
+    }
+
+    let result = tokenize(input).and_then(|tokens| {
+        let mut tokens = tokens.into_iter().peekable();
+        let list = parse_list(&mut tokens, &[])?;
+        // What stops a list inside a compound command, like `;;`, cannot
+        // come first.
+        match tokens.next() {
+            Some(token) => Err(ShellError::Syntax(token_text(&token))),
+            None => Ok(list),
+        }
+    });
+    match result {
+        Ok(list) => Ok(Parsed::Complete(list)),
+        Err(ShellError::Incomplete) => Ok(Parsed::Incomplete),
+        Err(err) => Err(err),
+    }
+}
+
+/// Parses input that must hold whole commands, like the source of a
+/// command substitution.
+pub fn parse_command(input: &str) -> Result<List, ShellError> {
+    match parse(input)? {
+        Parsed::Complete(list) => Ok(list),
+        Parsed::Incomplete => Err(ShellError::Incomplete),
+    }
+}
+
+/// Reads the next command from `source`, asking it for more lines as long
+/// as the command is incomplete. Returns its text and its syntax tree, or
+/// `None` at end of input.
+pub fn read_command(source: &mut dyn LineSource) -> Result<Option<(String, List)>, ShellError> {
+    let Some(mut text) = source.read_line(false)? else {
+        return Ok(None);
+    };
+
+    loop {
+        match parse(&text)? {
+            Parsed::Complete(list) => return Ok(Some((text, list))),
+            Parsed::Incomplete => {
+                let Some(line) = source.read_line(true)? else {
+                    return Err(ShellError::Incomplete);
+                };
+                text.push('\n');
+                text.push_str(&line);
+            }
+        }
+    }
+}
+
+/// Splits `input` into words as the shell reads them, quotes removed, for
+/// lines the shell wrote to a file itself. `None` when it holds anything
+/// but plain words, like an operator or an expansion, which is not to be
+/// run.
+pub fn split_words(input: &str) -> Option<Vec<String>> {
+    let tokens = tokenize(input).ok()?;
+
+    let mut words = Vec::new();
+    for token in tokens {
+        let Token::Word(word) = token else {
+            return None;
+        };
+        let mut text = String::new();
+        for part in word.parts {
+            match part {
+                WordPart::Literal(part) | WordPart::Quoted(part) => text.push_str(&part),
+                _ => return None,
+            }
+        }
+        words.push(text);
+    }
+    Some(words)
+}
+
+type Tokens = Peekable<vec::IntoIter<Token>>;
+
+/// Words with a meaning of their own at the start of a command.
+const RESERVED_WORDS: &[&str] = &[
+    "if", "then", "elif", "else", "fi", "while", "until", "do", "done", "for", "case", "esac",
+    "{", "}", "function",
+];
+
+/// `and_or ((';' | '&' | newline) and_or)*`, blank lines allowed anywhere.
+/// Stops before any of the reserved words in `terminators`, which end the
+/// body of a compound command, and before the `;;` ending a `case` item.
+fn parse_list(tokens: &mut Tokens, terminators: &[&str]) -> Result<List, ShellError> {
+    let mut list = List::default();
+
+    loop {
+        skip_newlines(tokens);
+        if matches!(tokens.peek(), None | Some(Token::CaseEnd(_)))
+            || reserved_word(tokens.peek()).is_some_and(|word| terminators.contains(&word))
+        {
+            break;
+        }
+
+        let mut and_or = parse_and_or(tokens)?;
+
+        if matches!(tokens.peek(), Some(Token::CaseEnd(_))) {
+            list.items.push(and_or);
+            break;
+        }
+        match tokens.next() {
+            None => {
+                list.items.push(and_or);
+                break;
+            }
+            Some(Token::Ampersand) => {
+                and_or.background = true;
+                list.items.push(and_or);
+            }
+            Some(Token::Semicolon | Token::Newline) => list.items.push(and_or),
+            Some(other) => return Err(ShellError::Syntax(token_text(&other))),
+        }
+    }
+
+    Ok(list)
+}
+
+/// `pipeline (('&&' | '||') pipeline)*`
+fn parse_and_or(tokens: &mut Tokens) -> Result<AndOr, ShellError> {
+    let first = parse_pipeline(tokens)?;
+    let mut rest = Vec::new();
+
+    loop {
+        let connector = match tokens.peek() {
+            Some(Token::And) => Connector::And,
+            Some(Token::Or) => Connector::Or,
+            _ => break,
+        };
+        tokens.next();
+        skip_newlines(tokens);
+
+        rest.push((connector, parse_pipeline(tokens)?));
+    }
+
+    Ok(AndOr {
+        first,
+        rest,
+        background: false,
+    })
+}
+
+/// `command ('|' command)*`
+fn parse_pipeline(tokens: &mut Tokens) -> Result<Pipeline, ShellError> {
+    let mut pipeline = Pipeline::default();
+
+    loop {
+        pipeline.commands.push(parse_stage(tokens)?);
+
+        if tokens.next_if_eq(&Token::Pipe).is_none() {
+            break;
+        }
+        skip_newlines(tokens);
+    }
+
+    Ok(pipeline)
+}
+
+/// A simple command, a compound command followed by its redirections, or
+/// a function definition.
+fn parse_stage(tokens: &mut Tokens) -> Result<Stage, ShellError> {
+    match reserved_word(tokens.peek()) {
+        None if !matches!(tokens.peek(), Some(Token::Arithmetic(_))) => {
+            let mut command = parse_simple_command(tokens)?;
+            if tokens.peek() != Some(&Token::OpenParen) {
+                return Ok(Stage::Simple(command));
+            }
+
+            // `name()`: the command must be the name alone.
+            match command.words.pop() {
+                Some(name)
+                    if command.words.is_empty()
+                        && command.assignments.is_empty()
+                        && command.redirects.is_empty() =>
+                {
+                    parse_function(tokens, name)
+                }
+                _ => Err(ShellError::Syntax("(".to_string())),
+            }
+        }
+        Some("function") => {
+            tokens.next();
+            match tokens.next() {
+                Some(Token::Word(name)) => parse_function(tokens, name),
+                Some(token) => Err(ShellError::Syntax(token_text(&token))),
+                None => Err(ShellError::Incomplete),
+            }
+        }
+        _ => {
+            let (command, redirects) = parse_compound(tokens)?;
+            Ok(Stage::Compound(command, redirects))
+        }
+    }
+}
+
+/// A compound command and the redirections after it.
+fn parse_compound(tokens: &mut Tokens) -> Result<(CompoundCommand, Vec<Redirect>), ShellError> {
+    let command = match reserved_word(tokens.peek()) {
+        Some("if") => parse_if(tokens)?,
+        Some(keyword @ ("while" | "until")) => {
+            let until = keyword == "until";
+            parse_while(tokens, until)?
+        }
+        Some("for") => parse_for(tokens)?,
+        Some("case") => parse_case(tokens)?,
+        Some("{") => {
+            tokens.next();
+            let body = parse_body(tokens, &["}"])?;
+            expect(tokens, "}")?;
+            CompoundCommand::Group(body)
+        }
+        Some(keyword) => return Err(ShellError::Syntax(keyword.to_string())),
+        None => match tokens.next() {
+            Some(Token::Arithmetic(expression)) => {
+                CompoundCommand::Arithmetic(lex_word(&expression)?)
+            }
+            Some(token) => return Err(ShellError::Syntax(token_text(&token))),
+            None => return Err(ShellError::Incomplete),
+        },
+    };
+
+    let mut redirects = Vec::new();
+    while let Some(Token::Redirect(fd, op)) =
+        tokens.next_if(|token| matches!(token, Token::Redirect(..)))
+    {
+        redirects.push(parse_redirect(tokens, fd, op)?);
+    }
+
+    Ok((command, redirects))
+}
+
+/// The rest of a function definition after its name: `()`, which may be
+/// left out after `function`, then the body, a compound command.
+fn parse_function(tokens: &mut Tokens, name: Word) -> Result<Stage, ShellError> {
+    let name = match name.parts.as_slice() {
+        [WordPart::Literal(text)] if !text.contains(['$', '/', '=']) => text.clone(),
+        _ => return Err(ShellError::Syntax(name.to_string())),
+    };
+
+    if tokens.next_if_eq(&Token::OpenParen).is_some() {
+        match tokens.next() {
+            Some(Token::CloseParen) => {}
+            Some(token) => return Err(ShellError::Syntax(token_text(&token))),
+            None => return Err(ShellError::Incomplete),
+        }
+    }
+    skip_newlines(tokens);
+
+    let (body, redirects) = parse_compound(tokens)?;
+    Ok(Stage::Function(Rc::new(Function {
+        name,
+        body,
+        redirects,
+    })))
+}
+
+/// `if list then list (elif list then list)* (else list)? fi`
+fn parse_if(tokens: &mut Tokens) -> Result<CompoundCommand, ShellError> {
+    tokens.next();
+    let mut branches = Vec::new();
+    let mut otherwise = None;
+
+    loop {
+        let condition = parse_body(tokens, &["then"])?;
+        expect(tokens, "then")?;
+        let body = parse_body(tokens, &["elif", "else", "fi"])?;
+        branches.push((condition, body));
+
+        match tokens.next() {
+            Some(token) if is_reserved(&token, "elif") => continue,
+            Some(token) if is_reserved(&token, "else") => {
+                otherwise = Some(parse_body(tokens, &["fi"])?);
+                expect(tokens, "fi")?;
+                break;
+            }
+            Some(token) if is_reserved(&token, "fi") => break,
+            Some(token) => return Err(ShellError::Syntax(token_text(&token))),
+            None => return Err(ShellError::Incomplete),
+        }
+    }
+
+    Ok(CompoundCommand::If {
+        branches,
+        otherwise,
+    })
+}
+
+/// `(while | until) list do list done`
+fn parse_while(tokens: &mut Tokens, until: bool) -> Result<CompoundCommand, ShellError> {
+    tokens.next();
+    let condition = parse_body(tokens, &["do"])?;
+    let body = parse_do_group(tokens)?;
+
+    Ok(CompoundCommand::While {
+        condition,
+        body,
+        until,
+    })
+}
+
+/// `for name (in word*)? (';' | newline)? do list done`, or
+/// `for ((init; condition; step)) (';' | newline)? do list done`
+fn parse_for(tokens: &mut Tokens) -> Result<CompoundCommand, ShellError> {
+    tokens.next();
+
+    let name = match tokens.next() {
+        Some(Token::Arithmetic(expression)) => {
+            let [init, condition, step] = expression.split(';').collect::<Vec<_>>()[..] else {
+                return Err(ShellError::Syntax(format!("(({}))", expression)));
+            };
+            skip_separator(tokens);
+            return Ok(CompoundCommand::ArithmeticFor {
+                init: lex_word(init.trim())?,
+                condition: lex_word(condition.trim())?,
+                step: lex_word(step.trim())?,
+                body: parse_do_group(tokens)?,
+            });
+        }
+        Some(Token::Word(word)) => match word.parts.as_slice() {
+            [WordPart::Literal(name)] if is_valid_name(name) => name.clone(),
+            _ => return Err(ShellError::Syntax(word.to_string())),
+        },
+        Some(token) => return Err(ShellError::Syntax(token_text(&token))),
+        None => return Err(ShellError::Incomplete),
+    };
+
+    skip_newlines(tokens);
+    let mut words = None;
+    if tokens.next_if(|token| is_reserved(token, "in")).is_some() {
+        let mut list = Vec::new();
+        loop {
+            match tokens.next() {
+                Some(Token::Word(word)) => list.push(word),
+                Some(Token::Semicolon | Token::Newline) => break,
+                Some(token) => return Err(ShellError::Syntax(token_text(&token))),
+                None => return Err(ShellError::Incomplete),
+            }
+        }
+        words = Some(list);
+    } else {
+        skip_separator(tokens);
+    }
+
+    Ok(CompoundCommand::For {
+        name,
+        words,
+        body: parse_do_group(tokens)?,
+    })
+}
+
+/// `case word in item* esac`, each item being
+/// `'('? pattern ('|' pattern)* ')' list (';;' | ';&' | ';;&')`; the last
+/// one may leave out its `;;`.
+fn parse_case(tokens: &mut Tokens) -> Result<CompoundCommand, ShellError> {
+    tokens.next();
+
+    let word = match tokens.next() {
+        Some(Token::Word(word)) => word,
+        Some(token) => return Err(ShellError::Syntax(token_text(&token))),
+        None => return Err(ShellError::Incomplete),
+    };
+    skip_newlines(tokens);
+    expect(tokens, "in")?;
+
+    let mut items = Vec::new();
+    loop {
+        skip_newlines(tokens);
+        if tokens.next_if(|token| is_reserved(token, "esac")).is_some() {
+            break;
+        }
+
+        tokens.next_if_eq(&Token::OpenParen);
+        let mut patterns = Vec::new();
+        loop {
+            match tokens.next() {
+                Some(Token::Word(pattern)) => patterns.push(pattern),
+                Some(token) => return Err(ShellError::Syntax(token_text(&token))),
+                None => return Err(ShellError::Incomplete),
+            }
+            match tokens.next() {
+                Some(Token::Pipe) => continue,
+                Some(Token::CloseParen) => break,
+                Some(token) => return Err(ShellError::Syntax(token_text(&token))),
+                None => return Err(ShellError::Incomplete),
+            }
+        }
+
+        let body = parse_list(tokens, &["esac"])?;
+        let terminator = match tokens.next() {
+            Some(Token::CaseEnd(terminator)) => terminator,
+            Some(token) if is_reserved(&token, "esac") => {
+                items.push(CaseItem {
+                    patterns,
+                    body,
+                    terminator: CaseTerminator::Break,
+                });
+                break;
+            }
+            Some(token) => return Err(ShellError::Syntax(token_text(&token))),
+            None => return Err(ShellError::Incomplete),
+        };
+        items.push(CaseItem {
+            patterns,
+            body,
+            terminator,
+        });
+    }
+
+    Ok(CompoundCommand::Case { word, items })
+}
+
+/// `do list done`, after any blank lines.
+fn parse_do_group(tokens: &mut Tokens) -> Result<List, ShellError> {
+    skip_newlines(tokens);
+    expect(tokens, "do")?;
+    let body = parse_body(tokens, &["done"])?;
+    expect(tokens, "done")?;
+    Ok(body)
+}
+
+/// The list in a compound command, which may not be empty.
+fn parse_body(tokens: &mut Tokens, terminators: &[&str]) -> Result<List, ShellError> {
+    let list = parse_list(tokens, terminators)?;
+    if list.is_empty() {
+        return Err(match tokens.peek() {
+            Some(token) => ShellError::Syntax(token_text(token)),
+            None => ShellError::Incomplete,
+        });
+    }
+    Ok(list)
+}
+
+/// Takes the reserved word `keyword`; running out of tokens first means the
+/// command goes on on the next line.
+fn expect(tokens: &mut Tokens, keyword: &str) -> Result<(), ShellError> {
+    match tokens.next() {
+        Some(token) if is_reserved(&token, keyword) => Ok(()),
+        Some(token) => Err(ShellError::Syntax(token_text(&token))),
+        None => Err(ShellError::Incomplete),
+    }
+}
+
+/// The reserved word `token` is, if any.
+fn reserved_word(token: Option<&Token>) -> Option<&'static str> {
+    RESERVED_WORDS
+        .iter()
+        .find(|keyword| token.is_some_and(|token| is_reserved(token, keyword)))
+        .copied()
+}
+
+/// Whether `token` is the unquoted word `keyword`.
+fn is_reserved(token: &Token, keyword: &str) -> bool {
+    match token {
+        Token::Word(word) => {
+            matches!(word.parts.as_slice(), [WordPart::Literal(text)] if text == keyword)
+        }
+        _ => false,
+    }
+}
+
+fn parse_simple_command(tokens: &mut Tokens) -> Result<SimpleCommand, ShellError> {
+    let mut command = SimpleCommand::default();
+
+    while let Some(token) =
+        tokens.next_if(|token| matches!(token, Token::Word(_) | Token::Redirect(..)))
+    {
+        match token {
+            Token::Word(word) => match assignment(&word) {
+                Some(assignment) if command.words.is_empty() => {
+                    command.assignments.push(assignment)
+                }
+                _ => command.words.push(word),
+            },
+            Token::Redirect(fd, op) => command.redirects.push(parse_redirect(tokens, fd, op)?),
+            _ => unreachable!(),
+        }
+    }
+
+    if command.assignments.is_empty() && command.words.is_empty() && command.redirects.is_empty()
+    {
+        // Nothing after `|`, `&&` or `||`: the command goes on on the next
+        // line.
+        return Err(match tokens.peek() {
+            Some(token) => ShellError::Syntax(token_text(token)),
+            None => ShellError::Incomplete,
+        });
+    }
+
+    Ok(command)
+}
+
+/// The target of a redirection operator, `fd` being the descriptor written
+/// in front of it.
+fn parse_redirect(
+    tokens: &mut Tokens,
+    fd: Option<i32>,
+    op: RedirectOp,
+) -> Result<Redirect, ShellError> {
+    let target = match tokens.next() {
+        Some(Token::Word(target)) => target,
+        Some(other) => return Err(ShellError::Syntax(token_text(&other))),
+        None => return Err(ShellError::Syntax("newline".to_string())),
+    };
+
+    Ok(Redirect {
+        fd: fd.unwrap_or(default_fd(op)),
+        op,
+        target,
+    })
+}
+
+/// Skips the optional `;` or newline before `do`.
+fn skip_separator(tokens: &mut Tokens) {
+    tokens.next_if(|token| matches!(token, Token::Semicolon | Token::Newline));
+}
+
+fn skip_newlines(tokens: &mut Tokens) {
+    while tokens.next_if_eq(&Token::Newline).is_some() {}
+}
+
+/// Reads a word as `NAME=value` when it starts with an unquoted valid name
+/// followed by `=`.
+fn assignment(word: &Word) -> Option<Assignment> {
+    let Some(WordPart::Literal(first)) = word.parts.first() else {
+        return None;
+    };
+    let (name, value) = split_assignment(first)?;
+
+    let mut parts = Vec::new();
+    if !value.is_empty() {
+        parts.push(WordPart::Literal(value.to_string()));
+    }
+    parts.extend(word.parts[1..].iter().cloned());
+
+    Some(Assignment {
+        name: name.to_string(),
+        value: Word { parts },
+    })
+}
+
+fn default_fd(op: RedirectOp) -> i32 {
+    match op {
+        RedirectOp::Read | RedirectOp::DupRead => 0,
+        _ => 1,
+    }
+}
+
+fn token_text(token: &Token) -> String {
+    match token {
+        Token::Word(word) => word.to_string(),
+        Token::Pipe => "|".to_string(),
+        Token::And => "&&".to_string(),
+        Token::Or => "||".to_string(),
+        Token::Semicolon => ";".to_string(),
+        Token::Ampersand => "&".to_string(),
+        Token::Newline => "newline".to_string(),
+        Token::Redirect(_, op) => op.to_string(),
+        Token::Arithmetic(expression) => format!("(({}))", expression),
+        Token::CaseEnd(terminator) => terminator.to_string(),
+        Token::OpenParen => "(".to_string(),
+        Token::CloseParen => ")".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(input: &str) -> ReaderSource<&[u8]> {
+        ReaderSource::new(input.as_bytes())
+    }
+
+    /// The function `input` defines.
+    fn function(input: &str) -> Function {
+        let list = parse_command(input).unwrap();
+        match &list.items[0].first.commands[..] {
+            [Stage::Function(function)] => (**function).clone(),
+            stages => panic!("not a function: {:?}", stages),
+        }
+    }
+
+    /// The compound command `input` is made of.
+    fn compound(input: &str) -> CompoundCommand {
+        let list = parse_command(input).unwrap();
+        match &list.items[..] {
+            [and_or] => match &and_or.first.commands[..] {
+                [Stage::Compound(command, _)] => command.clone(),
+                stages => panic!("not a compound command: {:?}", stages),
+            },
+            items => panic!("not one command: {:?}", items),
+        }
+    }
+
+    #[test]
+    fn reads_one_command_per_line() {
+        let mut input = source("echo a\necho b; echo c\n");
+
+        let (text, list) = read_command(&mut input).unwrap().unwrap();
+        assert_eq!(text, "echo a");
+        assert_eq!(list.items.len(), 1);
+
+        let (text, list) = read_command(&mut input).unwrap().unwrap();
+        assert_eq!(text, "echo b; echo c");
+        assert_eq!(list.items.len(), 2);
+
+        assert!(read_command(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn reads_more_lines_until_the_command_is_complete() {
+        let mut input = source("echo a &&\necho b ||\necho c\necho after\n");
+
+        let (text, list) = read_command(&mut input).unwrap().unwrap();
+        assert_eq!(text, "echo a &&\necho b ||\necho c");
+        assert_eq!(list.items[0].rest.len(), 2);
+
+        let (text, _) = read_command(&mut input).unwrap().unwrap();
+        assert_eq!(text, "echo after");
+    }
+
+    #[test]
+    fn reads_more_lines_until_the_compound_command_is_complete() {
+        let mut input = source("for i in 1 2\ndo echo $i\ndone\necho after\n");
+
+        let (text, list) = read_command(&mut input).unwrap().unwrap();
+        assert_eq!(text, "for i in 1 2\ndo echo $i\ndone");
+        assert!(matches!(
+            list.items[0].first.commands[0],
+            Stage::Compound(CompoundCommand::For { .. }, _)
+        ));
+
+        let (text, _) = read_command(&mut input).unwrap().unwrap();
+        assert_eq!(text, "echo after");
+    }
+
+    #[test]
+    fn continues_open_quotes_pipes_and_backslashes() {
+        let mut input = source("echo 'a\nb'\necho x |\ncat\necho a \\\nb\n");
+
+        let (text, list) = read_command(&mut input).unwrap().unwrap();
+        assert_eq!(text, "echo 'a\nb'");
+        let Stage::Simple(command) = &list.items[0].first.commands[0] else {
+            panic!("not a simple command");
+        };
+        assert_eq!(command.words[1].parts, vec![WordPart::Quoted("a\nb".to_string())]);
+
+        let (_, list) = read_command(&mut input).unwrap().unwrap();
+        assert_eq!(list.items[0].first.commands.len(), 2);
+
+        let (_, list) = read_command(&mut input).unwrap().unwrap();
+        let Stage::Simple(command) = &list.items[0].first.commands[0] else {
+            panic!("not a simple command");
+        };
+        assert_eq!(command.words.len(), 3);
+    }
+
+    #[test]
+    fn end_of_input_inside_a_command_is_an_error() {
+        for input in ["echo a &&\n", "echo 'open\n", "echo a |\n", "if true; then\necho hi\n"] {
+            let result = read_command(&mut source(input));
+            assert!(matches!(result, Err(ShellError::Incomplete)), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn parse_tells_incomplete_input_from_syntax_errors() {
+        assert!(matches!(parse("echo \"open"), Ok(Parsed::Incomplete)));
+        assert!(matches!(parse("echo a &&"), Ok(Parsed::Incomplete)));
+        assert!(matches!(parse("echo ; ;"), Err(ShellError::Syntax(token)) if token == ";"));
+        assert!(matches!(parse("| cat"), Err(ShellError::Syntax(_))));
+        assert!(matches!(parse("   "), Ok(Parsed::Complete(list)) if list.is_empty()));
+    }
+
+    #[test]
+    fn split_words_only_takes_plain_words() {
+        assert_eq!(
+            split_words("complete -W 'a b' x"),
+            Some(vec!["complete".into(), "-W".into(), "a b".into(), "x".into()])
+        );
+        assert_eq!(split_words("complete -W x y; rm -rf ~"), None);
+        assert_eq!(split_words("complete -W $(date) y"), None);
+    }
+
+    #[test]
+    fn parses_if_elif_and_else() {
+        let CompoundCommand::If { branches, otherwise } =
+            compound("if a; then b; elif c\nthen d1\nd2\nelse e; fi")
+        else {
+            panic!("not an if");
+        };
+        let branches: Vec<(String, String)> = branches
+            .iter()
+            .map(|(condition, body)| (condition.to_string(), body.to_string()))
+            .collect();
+        assert_eq!(
+            branches,
+            [("a;".to_string(), "b;".to_string()), ("c;".to_string(), "d1; d2;".to_string())]
+        );
+        assert_eq!(otherwise.unwrap().to_string(), "e;");
+
+        assert!(matches!(
+            compound("if a; then b; fi"),
+            CompoundCommand::If { otherwise: None, .. }
+        ));
+        assert!(matches!(parse("if a; then b"), Ok(Parsed::Incomplete)));
+        assert!(matches!(parse("if a; fi"), Err(ShellError::Syntax(token)) if token == "fi"));
+        assert!(matches!(parse("then b"), Err(ShellError::Syntax(token)) if token == "then"));
+    }
+
+    #[test]
+    fn parses_while_and_until_loops() {
+        let CompoundCommand::While { condition, body, until } =
+            compound("while a; b\ndo c; done")
+        else {
+            panic!("not a while loop");
+        };
+        assert_eq!((condition.to_string(), body.to_string()), ("a; b;".into(), "c;".into()));
+        assert!(!until);
+
+        let command = compound("until a; do b; done");
+        assert!(matches!(command, CompoundCommand::While { until: true, .. }));
+        assert_eq!(command.to_string(), "until a; do b; done");
+
+        assert!(matches!(parse("while a; do b"), Ok(Parsed::Incomplete)));
+        let result = parse("while a; done");
+        assert!(matches!(result, Err(ShellError::Syntax(token)) if token == "done"));
+    }
+
+    #[test]
+    fn parses_for_loops() {
+        let CompoundCommand::For { name, words, body } = compound("for i in a 'b c'; do d; done")
+        else {
+            panic!("not a for loop");
+        };
+        assert_eq!(name, "i");
+        assert_eq!(words.unwrap().len(), 2);
+        assert_eq!(body.to_string(), "d;");
+        assert!(matches!(compound("for i; do d; done"), CompoundCommand::For { words: None, .. }));
+
+        let CompoundCommand::ArithmeticFor { init, condition, step, .. } =
+            compound("for ((i = 0; i < $n; i++)); do d; done")
+        else {
+            panic!("not an arithmetic for loop");
+        };
+        assert_eq!(init.to_string(), "i = 0");
+        assert!(condition.parts.iter().any(|part| matches!(part, WordPart::Param(_))));
+        assert_eq!(step.to_string(), "i++");
+        assert!(matches!(parse("for ((i = 0)); do d; done"), Err(ShellError::Syntax(_))));
+    }
+
+    #[test]
+    fn break_and_continue_are_commands_with_a_count() {
+        let CompoundCommand::While { body, .. } =
+            compound("while a; do break 2; continue 3; done")
+        else {
+            panic!("not a while loop");
+        };
+        let words: Vec<String> = body
+            .items
+            .iter()
+            .map(|and_or| match &and_or.first.commands[..] {
+                [Stage::Simple(command)] => {
+                    command.words.iter().map(Word::to_string).collect::<Vec<_>>().join(" ")
+                }
+                stages => panic!("not a simple command: {:?}", stages),
+            })
+            .collect();
+        assert_eq!(words, ["break 2", "continue 3"]);
+    }
+
+    #[test]
+    fn parses_case_items_and_their_terminators() {
+        let CompoundCommand::Case { word, items } =
+            compound("case $1 in\n(a|b) x;;\nc) y1; y2;&\n*) z;;&\nesac")
+        else {
+            panic!("not a case");
+        };
+        assert_eq!(word.to_string(), "${1}");
+        let items: Vec<(Vec<String>, String, CaseTerminator)> = items
+            .iter()
+            .map(|item| {
+                let patterns = item.patterns.iter().map(Word::to_string).collect();
+                (patterns, item.body.to_string(), item.terminator)
+            })
+            .collect();
+        assert_eq!(
+            items,
+            [
+                (vec!["a".into(), "b".into()], "x;".into(), CaseTerminator::Break),
+                (vec!["c".into()], "y1; y2;".into(), CaseTerminator::FallThrough),
+                (vec!["*".into()], "z;".into(), CaseTerminator::Continue),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_last_case_item_needs_no_terminator() {
+        let CompoundCommand::Case { items, .. } = compound("case a in a) b\nesac") else {
+            panic!("not a case");
+        };
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].terminator, CaseTerminator::Break);
+
+        let CompoundCommand::Case { items, .. } = compound("case a in (a) ;; esac") else {
+            panic!("not a case");
+        };
+        assert!(items[0].body.is_empty());
+        assert!(matches!(
+            compound("case a in esac"),
+            CompoundCommand::Case { items, .. } if items.is_empty()
+        ));
+
+        assert!(matches!(parse("case a in a) b;;"), Ok(Parsed::Incomplete)));
+        assert!(matches!(parse("case a b"), Err(ShellError::Syntax(token)) if token == "b"));
+    }
+
+    #[test]
+    fn parses_function_definitions() {
+        let f = function("f() { echo a; }");
+        assert_eq!(f.name, "f");
+        assert_eq!(f.body.to_string(), "{ echo a; }");
+        assert!(f.redirects.is_empty());
+
+        let g = function("function g\n{\necho b\n} >out 2>&1");
+        assert_eq!(g.name, "g");
+        assert!(matches!(g.body, CompoundCommand::Group(_)));
+        assert_eq!(g.redirects.len(), 2);
+
+        let h = function("function h() while a; do b; done");
+        assert!(matches!(h.body, CompoundCommand::While { .. }));
+
+        assert!(matches!(parse("f() {"), Ok(Parsed::Incomplete)));
+        assert!(matches!(parse("f() echo"), Err(ShellError::Syntax(token)) if token == "echo"));
+        assert!(matches!(parse("echo f() { a; }"), Err(ShellError::Syntax(token)) if token == "("));
+        assert!(matches!(parse("function $x { a; }"), Err(ShellError::Syntax(_))));
+    }
+
+    #[test]
+    fn local_and_return_are_commands_in_the_body() {
+        let f = function("f() { local x=1 y; return 3; }");
+        let CompoundCommand::Group(body) = f.body else {
+            panic!("not a group");
+        };
+        let commands: Vec<(Vec<String>, usize)> = body
+            .items
+            .iter()
+            .map(|and_or| match &and_or.first.commands[..] {
+                [Stage::Simple(command)] => {
+                    let words = command.words.iter().map(Word::to_string).collect();
+                    (words, command.assignments.len())
+                }
+                stages => panic!("not a simple command: {:?}", stages),
+            })
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                (vec!["local".into(), "x=1".into(), "y".into()], 0),
+                (vec!["return".into(), "3".into()], 0),
+            ]
+        );
+    }
+
+    /// `input` with `aliases` expanded, as the shell would run it.
+    fn with_aliases(input: &str, aliases: &[(&str, &str)]) -> String {
+        let aliases = aliases
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let mut list = parse_command(input).unwrap();
+        expand_aliases(&mut list, &aliases).unwrap();
+        list.to_string()
+    }
+
+    #[test]
+    fn expands_aliases_in_command_position() {
+        let aliases = [("ll", "ls -l"), ("both", "a; b")];
+        assert_eq!(with_aliases("ll /tmp", &aliases), "ls -l /tmp;");
+        assert_eq!(with_aliases("x=1 ll | ll && ll", &aliases), "x=1 ls -l | ls -l && ls -l;");
+        assert_eq!(with_aliases("if ll; then ll; fi", &aliases), "if ls -l; then ls -l; fi;");
+        assert_eq!(with_aliases("both c", &aliases), "{ a; b c; };");
+
+        // Only an unquoted command name is an alias.
+        assert_eq!(with_aliases("echo ll", &aliases), "echo ll;");
+        assert_eq!(with_aliases("'ll'", &aliases), r#""ll";"#);
+    }
+
+    #[test]
+    fn an_alias_ending_with_a_blank_expands_the_next_word() {
+        let aliases = [("sudo", "sudo "), ("ll", "ls -l"), ("e", "echo")];
+        assert_eq!(with_aliases("sudo ll x", &aliases), "sudo ls -l x;");
+        assert_eq!(with_aliases("sudo sudo ll", &aliases), "sudo sudo ls -l;");
+        assert_eq!(with_aliases("e ll", &aliases), "echo ll;");
+    }
+
+    #[test]
+    fn an_alias_is_not_expanded_inside_itself() {
+        assert_eq!(with_aliases("ls a", &[("ls", "ls -F")]), "ls -F a;");
+        assert_eq!(with_aliases("a", &[("a", "b x"), ("b", "a y")]), "a y x;");
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    os::fd::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+};
+
+use crate::{error::ShellError, parser::RedirectOp};
+
+/// A redirection whose target word has been expanded.
+pub struct Redirection {
+    pub fd: RawFd,
+    pub op: RedirectOp,
+    pub target: String,
+}
+
+/// Applies redirections to the current process, left to right.
+pub fn apply(redirects: &[Redirection]) -> Result<(), ShellError> {
+    for redirect in redirects {
+        apply_one(redirect)?;
+    }
+    Ok(())
+}
+
+fn apply_one(redirect: &Redirection) -> Result<(), ShellError> {
+    let target = &redirect.target;
+    let open_error = |err| ShellError::Redirect(target.clone(), err);
+
+    match redirect.op {
+        RedirectOp::Read => {
+            let file = File::open(target).map_err(open_error)?;
+            dup_onto(&file, redirect.fd, target)
+        }
+        RedirectOp::Write | RedirectOp::Append => {
+            let file = open_for_writing(target, redirect.op == RedirectOp::Append)
+                .map_err(open_error)?;
+            dup_onto(&file, redirect.fd, target)
+        }
+        RedirectOp::WriteAll | RedirectOp::AppendAll => {
+            let file = open_for_writing(target, redirect.op == RedirectOp::AppendAll)
+                .map_err(open_error)?;
+            dup_onto(&file, 1, target)?;
+            dup_onto(&file, 2, target)
+        }
+        RedirectOp::DupRead | RedirectOp::DupWrite => {
+            if target == "-" {
+                unsafe { libc::close(redirect.fd) };
+                return Ok(());
+            }
+
+            match target.parse::<RawFd>() {
+                Ok(source) => {
+                    if unsafe { libc::fcntl(source, libc::F_GETFD) } == -1 {
+                        return Err(ShellError::Redirect(
+                            target.clone(),
+                            io::Error::from_raw_os_error(libc::EBADF),
+                        ));
+                    }
+                    dup_raw(source, redirect.fd, target)
+                }
+                // `>& file` is an old spelling of `&> file`.
+                Err(_) if redirect.op == RedirectOp::DupWrite && redirect.fd == 1 => {
+                    apply_one(&Redirection {
+                        fd: 1,
+                        op: RedirectOp::WriteAll,
+                        target: target.clone(),
+                    })
+                }
+                Err(_) => Err(ShellError::Other(format!("{}: ambiguous redirect", target))),
+            }
+        }
+    }
+}
+
+fn open_for_writing(path: &str, append: bool) -> io::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+}
+
+fn dup_onto(file: &File, fd: RawFd, target: &str) -> Result<(), ShellError> {
+    dup_raw(file.as_raw_fd(), fd, target)
+}
+
+fn dup_raw(source: RawFd, fd: RawFd, target: &str) -> Result<(), ShellError> {
+    if source != fd && unsafe { libc::dup2(source, fd) } == -1 {
+        return Err(ShellError::Redirect(
+            target.to_string(),
+            io::Error::last_os_error(),
+        ));
+    }
+    Ok(())
+}
+
+/// Keeps copies of the descriptors a set of redirections is about to
+/// replace, and puts them back when dropped.
+///
+/// Builtins run inside the shell process, so their redirections must be
+/// undone once they finish.
+pub struct SavedFds {
+    saved: Vec<(RawFd, Option<OwnedFd>)>,
+}
+
+impl SavedFds {
+    pub fn save(redirects: &[Redirection]) -> Self {
+        let mut saved: Vec<(RawFd, Option<OwnedFd>)> = Vec::new();
+
+        for redirect in redirects {
+            let fds: &[RawFd] = match redirect.op {
+                RedirectOp::WriteAll | RedirectOp::AppendAll => &[1, 2],
+                _ => &[redirect.fd],
+            };
+
+            for &fd in fds {
+                if saved.iter().any(|(saved_fd, _)| *saved_fd == fd) {
+                    continue;
+                }
+
+                let copy = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 10) };
+                let copy = (copy != -1).then(|| unsafe { OwnedFd::from_raw_fd(copy) });
+                saved.push((fd, copy));
+            }
+        }
+
+        Self { saved }
+    }
+}
+
+impl Drop for SavedFds {
+    fn drop(&mut self) {
+        for (fd, copy) in self.saved.drain(..).rev() {
+            match copy {
+                Some(copy) => unsafe {
+                    let raw = copy.into_raw_fd();
+                    libc::dup2(raw, fd);
+                    libc::close(raw);
+                },
+                None => unsafe {
+                    libc::close(fd);
+                },
+            }
+        }
+    }
+}
@@ -1,97 +1,1595 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env::current_dir,
-    io::{Write, stdin, stdout},
+    fs::{self, File},
+    io::{
+        self, BufRead, BufReader, IsTerminal, LineWriter, PipeReader, PipeWriter, Read, Write, pipe,
+        stdout,
+    },
+    os::{fd::AsFd, unix::process::ExitStatusExt},
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
 };
 
 use crate::{
-    commands::{Command, *},
-    error::ShellError,
+    commands::{Command, Context, *},
+    error::{ShellError, describe},
 };
 use crate::{
-    parser,
-    utils::{print_cur_dir, print_welcome},
+    completion::{self, Spec},
+    editor,
+    exec,
+    expand::{evaluate_arithmetic, expand_word, expand_words, match_pattern},
+    history::{self, History},
+    jobs::{self, Job, JobState, Jobs, Process},
+    options::Options,
+    parser::{
+        self, AndOr, CaseItem, CaseTerminator, CompoundCommand, Connector, Function, LineSource,
+        List, Pipeline, ReaderSource, Redirect, SimpleCommand, Stage, Word,
+    },
+    redirect::{self, Redirection, SavedFds},
+    signals,
+    utils::{self, print_welcome},
+    variables::{Variables, quote},
 };
 
+/// `$0` when no script is running.
+pub const NAME: &str = "0shell";
+
+/// The file run at startup, in the home directory.
+const RC_FILE: &str = ".0shellrc";
+
+/// How deep function calls may nest, which stops runaway recursion before
+/// it overflows the stack.
+const MAX_FUNCTION_DEPTH: usize = 200;
+
 pub struct Shell {
-    commands: HashMap<String, Box<dyn Command>>,
+    commands: HashMap<String, Rc<dyn Command>>,
+    /// Shell functions by name, which come before builtins of the same
+    /// name.
+    functions: BTreeMap<String, Rc<Function>>,
+    /// How many function calls are running.
+    function_depth: usize,
+    /// Aliases set with `alias`, by name.
+    aliases: BTreeMap<String, String>,
+    vars: Variables,
+    options: Options,
+    last_status: i32,
+    exit_status: Option<i32>,
+    /// The status of the last `$(...)` run while expanding a command.
+    substitution_status: Option<i32>,
+    /// The shell's own pid for `$$`, which stays the same in subshells.
+    pid: u32,
+    jobs: Jobs,
+    /// Whether jobs get their own process group and the terminal, which is
+    /// the case when the shell reads commands from a terminal.
+    job_control: bool,
+    /// The shell's process group and terminal modes, taken back when a
+    /// foreground job ends or stops.
+    pgid: libc::pid_t,
+    termios: Option<libc::termios>,
+    /// The pid of the last background job, for `$!`.
+    last_background: Option<libc::pid_t>,
+    history: History,
+    /// Completion specs set with `complete`, by command name.
+    completions: BTreeMap<String, Spec>,
+    /// `$0`: the script being run, or the shell's name.
+    name: String,
+    /// `$1`, `$2`...
+    positional: Vec<String>,
+    /// How many loops are running, for `break` and `continue`.
+    loop_depth: usize,
+    /// A `break` or `continue` on its way to the loop it is for.
+    flow: Option<Flow>,
+}
+
+/// Leaves loops early, from `break n` and `continue n`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flow {
+    /// Ends the `n` innermost loops.
+    Break(usize),
+    /// Ends the `n - 1` innermost loops and goes on with the next
+    /// iteration of the one around them.
+    Continue(usize),
+    /// Ends the running function, which returns this status.
+    Return(i32),
 }
 
 impl Shell {
-    pub fn new() -> Self {
+    /// Creates the shell. An interactive shell reading from a terminal
+    /// catches Ctrl+C and controls jobs; a shell running a script does not.
+    pub fn new(interactive: bool) -> Self {
         let mut shell = Self {
             commands: HashMap::new(),
+            functions: BTreeMap::new(),
+            function_depth: 0,
+            aliases: BTreeMap::new(),
+            vars: Variables::from_env(),
+            options: Options::default(),
+            last_status: 0,
+            exit_status: None,
+            substitution_status: None,
+            pid: process::id(),
+            jobs: Jobs::default(),
+            job_control: false,
+            pgid: 0,
+            termios: None,
+            last_background: None,
+            history: History::default(),
+            completions: BTreeMap::new(),
+            name: NAME.to_string(),
+            positional: Vec::new(),
+            loop_depth: 0,
+            flow: None,
         };
 
         shell.register_commands();
+        if interactive && io::stdin().is_terminal() {
+            signals::install();
+            shell.init_job_control();
+        }
         shell
     }
 
+    /// Puts the shell in its own process group in charge of the terminal,
+    /// when there is one.
+    fn init_job_control(&mut self) {
+        if !io::stdin().is_terminal() {
+            return;
+        }
+
+        unsafe {
+            // Started in the background: wait to be brought to the
+            // foreground, as other shells do.
+            let mut pgid = libc::getpgrp();
+            while libc::tcgetpgrp(0) != pgid {
+                libc::kill(-pgid, libc::SIGTTIN);
+                pgid = libc::getpgrp();
+            }
+
+            for signal in [libc::SIGTSTP, libc::SIGTTIN, libc::SIGTTOU, libc::SIGQUIT] {
+                libc::signal(signal, libc::SIG_IGN);
+            }
+
+            let pid = libc::getpid();
+            if pgid != pid && libc::setpgid(0, pid) == -1 {
+                return;
+            }
+            self.pgid = pid;
+        }
+
+        jobs::give_terminal(self.pgid);
+        self.termios = jobs::terminal_modes();
+        self.job_control = true;
+    }
+
     fn register_commands(&mut self) {
         self.commands
-            .insert("exit".to_owned(), Box::new(ExitCommand));
-        self.commands.insert("pwd".to_owned(), Box::new(PwdCommand));
+            .insert("exit".to_owned(), Rc::new(ExitCommand));
+        self.commands.insert("pwd".to_owned(), Rc::new(PwdCommand));
+        self.commands
+            .insert("echo".to_owned(), Rc::new(EchoCommand));
+        self.commands
+            .insert("mkdir".to_owned(), Rc::new(MkdirCommand));
+        self.commands.insert("cd".to_owned(), Rc::new(CdCommand));
+        self.commands.insert("ls".to_owned(), Rc::new(LsCommand));
+        self.commands.insert("rm".to_owned(), Rc::new(RmCommand));
+        self.commands.insert("mv".to_owned(), Rc::new(MvCommand));
+        self.commands.insert("cp".to_owned(), Rc::new(CpCommand));
+        self.commands.insert("cat".to_owned(), Rc::new(CatCommand));
+        self.commands
+            .insert("export".to_owned(), Rc::new(ExportCommand));
+        self.commands
+            .insert("unset".to_owned(), Rc::new(UnsetCommand));
+        self.commands.insert("env".to_owned(), Rc::new(EnvCommand));
+        self.commands.insert("set".to_owned(), Rc::new(SetCommand));
+        self.commands.insert("jobs".to_owned(), Rc::new(JobsCommand));
+        self.commands.insert("fg".to_owned(), Rc::new(FgCommand));
+        self.commands.insert("bg".to_owned(), Rc::new(BgCommand));
+        self.commands.insert("wait".to_owned(), Rc::new(WaitCommand));
+        self.commands
+            .insert("history".to_owned(), Rc::new(HistoryCommand));
         self.commands
-            .insert("echo".to_owned(), Box::new(EchoCommand));
+            .insert("complete".to_owned(), Rc::new(CompleteCommand));
         self.commands
-            .insert("mkdir".to_owned(), Box::new(MkdirCommand));
-        self.commands.insert("cd".to_owned(), Box::new(CdCommand));
-        self.commands.insert("ls".to_owned(), Box::new(LsCommand));
-        self.commands.insert("rm".to_owned(), Box::new(RmCommand));
-        self.commands.insert("mv".to_owned(), Box::new(MvCommand));
-        self.commands.insert("cp".to_owned(), Box::new(CpCommand));
-        self.commands.insert("cat".to_owned(), Box::new(CatCommand));
+            .insert("source".to_owned(), Rc::new(SourceCommand));
+        self.commands.insert(".".to_owned(), Rc::new(SourceCommand));
+        self.commands.insert("break".to_owned(), Rc::new(BreakCommand));
+        self.commands
+            .insert("continue".to_owned(), Rc::new(ContinueCommand));
+        self.commands
+            .insert("return".to_owned(), Rc::new(ReturnCommand));
+        self.commands.insert("local".to_owned(), Rc::new(LocalCommand));
+        self.commands
+            .insert("declare".to_owned(), Rc::new(DeclareCommand));
+        self.commands
+            .insert("alias".to_owned(), Rc::new(AliasCommand));
+        self.commands
+            .insert("unalias".to_owned(), Rc::new(UnaliasCommand));
+        self.commands
+            .insert("let".to_owned(), Rc::new(LetCommand));
     }
 
-    pub fn run_loop(&mut self) {
+    pub fn run_loop(&mut self) -> i32 {
         print_welcome();
 
+        let interactive = io::stdin().is_terminal();
+        if interactive {
+            self.load_history();
+            self.load_completions();
+        }
+
         loop {
-            match current_dir() {
-                Ok(path) => {
-                    print_cur_dir(path);
+            if self.exit_status.is_some() {
+                break;
+            }
+            signals::clear();
+            self.notify_jobs();
+
+            let command = parser::read_command(&mut Prompt::new(self));
+            match command {
+                Ok(None) => {
+                    println!("CTRL + D exit...");
+                    break;
                 }
-                Err(e) => {
-                    eprintln!("Error getting current directory: {}", e);
-                    print!("~$ ");
+                Ok(Some((input, mut list))) => {
+                    let control = history::Control::parse(&self.var("HISTCONTROL").unwrap_or_default());
+                    let size = self.history_size();
+                    self.history.add(&input, control, size);
+
+                    if let Err(err) = parser::expand_aliases(&mut list, &self.aliases) {
+                        eprintln!("{}", err);
+                        self.last_status = err.status();
+                    } else if !list.is_empty() {
+                        self.last_status = self.execute_list(&list);
+                    }
+                }
+                Err(err) => {
+                    // A syntax error, or Ctrl+C at the prompt, whose error
+                    // prints as nothing but a fresh line.
+                    eprintln!("{}", err);
+                    self.last_status = err.status();
+                }
+            };
+        }
+
+        if interactive {
+            self.save_history();
+            self.save_completions();
+        }
+        self.exit_status.unwrap_or(self.last_status)
+    }
+
+    /// Runs the rc file before the first prompt: `path`, or `~/.0shellrc`
+    /// when `None`, which may be missing.
+    pub fn load_rc(&mut self, path: Option<&Path>) {
+        let result = match path {
+            Some(path) => self.source(path),
+            None => {
+                let Some(home) = self.var("HOME") else {
+                    return;
+                };
+                let path = Path::new(&home).join(RC_FILE);
+                if !path.exists() {
+                    return;
+                }
+                self.source(&path)
+            }
+        };
+
+        if let Err(err) = result {
+            eprintln!("{}", err);
+        }
+    }
+
+    /// Runs the commands of a file in the current shell, as `source` does,
+    /// reading a command on as many lines as it takes to be complete.
+    /// Returns the status of the last command.
+    pub fn source(&mut self, path: &Path) -> Result<i32, ShellError> {
+        let file =
+            File::open(path).map_err(|err| ShellError::File(path.display().to_string(), err))?;
+        let mut source = ReaderSource::new(BufReader::new(file));
+        self.run_source(&path.display().to_string(), &mut source)
+    }
+
+    /// Runs a script, `$0` being its path and `args` the positional
+    /// parameters. Returns the status to exit with.
+    pub fn run_script(&mut self, path: &Path, args: Vec<String>) -> i32 {
+        self.name = path.display().to_string();
+        self.positional = args;
+
+        let result = self.source(path);
+        self.finish(result)
+    }
+
+    /// Runs the commands of `-c`, `$0` being `name` if given.
+    pub fn run_string(&mut self, commands: &str, name: Option<String>, args: Vec<String>) -> i32 {
+        if let Some(name) = name {
+            self.name = name;
+        }
+        self.positional = args;
+
+        let result = self.run_source(NAME, &mut ReaderSource::new(commands.as_bytes()));
+        self.finish(result)
+    }
+
+    /// The status a script ends with: the one given to `exit`, or that of
+    /// its last command.
+    fn finish(&mut self, result: Result<i32, ShellError>) -> i32 {
+        let status = match result {
+            Ok(status) => status,
+            // The script could not be read.
+            Err(err @ ShellError::File(..)) => {
+                eprintln!("{}: {}", NAME, err);
+                127
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                err.status()
+            }
+        };
+        self.exit_status.unwrap_or(status)
+    }
+
+    /// Runs the commands read from `source` one by one, `origin` naming
+    /// where they come from in error messages. A syntax error skips the
+    /// command it is in; running out of input in the middle of a command
+    /// ends the run.
+    fn run_source(&mut self, origin: &str, source: &mut dyn LineSource) -> Result<i32, ShellError> {
+        let mut status = 0;
+        loop {
+            let command = parser::read_command(source).and_then(|command| {
+                command
+                    .map(|(_, mut list)| {
+                        parser::expand_aliases(&mut list, &self.aliases)?;
+                        Ok(list)
+                    })
+                    .transpose()
+            });
+            status = match command {
+                Ok(None) => return Ok(status),
+                Ok(Some(list)) if list.is_empty() => continue,
+                Ok(Some(list)) => self.execute_list(&list),
+                Err(err) => {
+                    eprintln!("{}: {}", origin, err);
+                    err.status()
                 }
+            };
+            self.last_status = status;
+
+            if self.exit_status.is_some() || signals::interrupted() {
+                return Ok(status);
             }
+        }
+    }
+
+    /// The number of entries kept in memory, from `HISTSIZE`. The file
+    /// keeps as many unless `HISTFILESIZE` says otherwise.
+    fn history_size(&self) -> usize {
+        self.var("HISTSIZE")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(history::DEFAULT_SIZE)
+    }
+
+    /// `$HISTFILE`, or `~/.0shell_history`. `None` when `HISTFILE` is set
+    /// but empty, which turns saving off.
+    fn history_file(&self) -> Option<PathBuf> {
+        match self.var("HISTFILE") {
+            Some(path) if path.is_empty() => None,
+            Some(path) => Some(PathBuf::from(path)),
+            None => Some(PathBuf::from(self.var("HOME")?).join(history::DEFAULT_FILE)),
+        }
+    }
+
+    fn load_history(&mut self) {
+        let Some(path) = self.history_file() else {
+            return;
+        };
+        let size = self.history_size();
+        if let Err(err) = self.history.load(&path, size) {
+            eprintln!("history: {}: {}", path.display(), err);
+        }
+    }
+
+    fn save_history(&self) {
+        let Some(path) = self.history_file() else {
+            return;
+        };
+        let size = self
+            .var("HISTFILESIZE")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| self.history_size());
+        if let Err(err) = self.history.save(&path, size) {
+            eprintln!("history: {}: {}", path.display(), err);
+        }
+    }
+
+    fn completions_file(&self) -> Option<PathBuf> {
+        Some(PathBuf::from(self.var("HOME")?).join(completion::SPECS_FILE))
+    }
 
-            if let Err(e) = stdout().flush() {
-                eprintln!("Error flushing stdout: {}", e);
+    /// Reads back the `complete` commands saved by the previous session.
+    /// They are parsed rather than run: the file holds specs, not code.
+    fn load_completions(&mut self) {
+        let Some(path) = self.completions_file() else {
+            return;
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+            Err(err) => {
+                eprintln!("complete: {}: {}", path.display(), err);
+                return;
             }
+        };
+
+        for line in content.lines().filter(|line| line.starts_with("complete ")) {
+            let arguments = parser::split_words(line)
+                .and_then(|words| complete::parse_args(words[1..].to_vec()).ok())
+                .filter(|arguments| {
+                    !arguments.print
+                        && !arguments.remove
+                        && arguments.spec != Spec::default()
+                        && !arguments.names.is_empty()
+                });
+            let Some(arguments) = arguments else {
+                eprintln!("{}: not a completion spec: {}", path.display(), line);
+                continue;
+            };
+
+            for name in arguments.names {
+                self.completions.insert(name, arguments.spec.clone());
+            }
+        }
+    }
+
+    /// Saves the completion specs as the `complete` commands that set them.
+    fn save_completions(&self) {
+        let Some(path) = self.completions_file() else {
+            return;
+        };
+        if self.completions.is_empty() && !path.exists() {
+            return;
+        }
+
+        let mut content = String::new();
+        for (name, spec) in &self.completions {
+            content.push_str(&format!("complete {} {}\n", spec, quote(name)));
+        }
+        if let Err(err) = fs::write(&path, content) {
+            eprintln!("complete: {}: {}", path.display(), err);
+        }
+    }
+
+    pub fn completion(&self, name: &str) -> Option<&Spec> {
+        self.completions.get(name)
+    }
+
+    pub fn completions(&self) -> &BTreeMap<String, Spec> {
+        &self.completions
+    }
+
+    pub fn completions_mut(&mut self) -> &mut BTreeMap<String, Spec> {
+        &mut self.completions
+    }
 
-            let mut input = String::new();
-            match stdin().read_line(&mut input) {
+    pub fn builtin(&self, name: &str) -> Option<Rc<dyn Command>> {
+        self.commands.get(name).cloned()
+    }
+
+    pub fn builtin_names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(String::as_str)
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    /// The prompt shown before a command: the current directory.
+    fn prompt(&self) -> String {
+        match current_dir() {
+            Ok(path) => {
+                let home_dir = self.var("HOME").unwrap_or_else(|| "/".to_string());
+                utils::prompt(path, &home_dir)
+            }
+            Err(e) => {
+                eprintln!("Error getting current directory: {}", e);
+                "~$ ".to_string()
+            }
+        }
+    }
+
+    /// Shows `prompt` and reads a line, without its newline. `None` at end
+    /// of input.
+    fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        if io::stdin().is_terminal() {
+            // The shell completes words while the editor shows the history:
+            // lend the history out for the duration.
+            let history = std::mem::take(&mut self.history);
+            let fuzzy_search = self.options.fuzzysearch;
+            let result = editor::read_line(prompt, history.entries(), fuzzy_search, self);
+            self.history = history;
+            return result;
+        }
+
+        print!("{}", prompt);
+        stdout().flush()?;
+
+        let mut input = String::new();
+        if utils::read_line(&mut input)? == 0 {
+            return Ok(None);
+        }
+        if input.ends_with('\n') {
+            input.pop();
+        }
+        Ok(Some(input))
+    }
+
+    pub fn var(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(self.last_status.to_string()),
+            "$" => Some(self.pid.to_string()),
+            "!" => self.last_background.map(|pid| pid.to_string()),
+            "0" => Some(self.name.clone()),
+            "#" => Some(self.positional.len().to_string()),
+            "@" | "*" => Some(self.positional.join(" ")),
+            _ if name.chars().all(|ch| ch.is_ascii_digit()) => {
+                let index = name.parse::<usize>().ok()?.checked_sub(1)?;
+                self.positional.get(index).cloned()
+            }
+            _ => self.vars.get(name).map(str::to_string),
+        }
+    }
+
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    /// Replaces the positional parameters, returning the previous ones.
+    pub fn set_positional(&mut self, args: Vec<String>) -> Vec<String> {
+        std::mem::replace(&mut self.positional, args)
+    }
+
+    pub fn set_var(&mut self, name: &str, value: &str) {
+        self.vars.set(name, value);
+    }
+
+    pub fn vars(&self) -> &Variables {
+        &self.vars
+    }
+
+    pub fn vars_mut(&mut self) -> &mut Variables {
+        &mut self.vars
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    pub fn last_status(&self) -> i32 {
+        self.last_status
+    }
+
+    pub fn set_last_status(&mut self, status: i32) {
+        self.last_status = status;
+    }
+
+    /// Asks the shell to exit with `status` once the current command is done.
+    pub fn request_exit(&mut self, status: i32) {
+        self.exit_status = Some(status);
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Rc<Function>> {
+        self.functions.get(name)
+    }
+
+    /// The functions, sorted by name.
+    pub fn functions(&self) -> &BTreeMap<String, Rc<Function>> {
+        &self.functions
+    }
+
+    pub fn functions_mut(&mut self) -> &mut BTreeMap<String, Rc<Function>> {
+        &mut self.functions
+    }
+
+    /// The aliases, sorted by name.
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    pub fn aliases_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.aliases
+    }
+
+    /// Whether a function is running, for `local` and `return`.
+    pub fn in_function(&self) -> bool {
+        self.function_depth > 0
+    }
+
+    pub fn loop_depth(&self) -> usize {
+        self.loop_depth
+    }
+
+    /// Leaves the running loops as `flow` says once the current command is
+    /// done.
+    pub fn set_flow(&mut self, flow: Flow) {
+        self.flow = Some(flow);
+    }
+
+    /// Whether the rest of the commands must be skipped: the shell is
+    /// exiting, Ctrl+C was pressed, or a loop is being left.
+    fn stopping(&self) -> bool {
+        self.exit_status.is_some() || signals::interrupted() || self.flow.is_some()
+    }
+
+    fn execute_command(&mut self, input: &str) -> Result<i32, ShellError> {
+        let mut list = parser::parse_command(input)?;
+        parser::expand_aliases(&mut list, &self.aliases)?;
+
+        if list.is_empty() {
+            return Ok(self.last_status);
+        }
+
+        Ok(self.execute_list(&list))
+    }
+
+    fn execute_list(&mut self, list: &List) -> i32 {
+        for and_or in &list.items {
+            if and_or.background {
+                self.last_status = match self.run_in_background(and_or) {
+                    Ok(()) => 0,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        err.status()
+                    }
+                };
+            } else {
+                self.execute_and_or(and_or);
+            }
+
+            if self.stopping() {
+                break;
+            }
+        }
+
+        self.last_status
+    }
+
+    /// Runs the pipelines of an and-or list, skipping those after `&&` when
+    /// the previous status is a failure and after `||` when it is a success.
+    fn execute_and_or(&mut self, and_or: &AndOr) -> i32 {
+        let mut status = self.run_pipeline(&and_or.first);
+
+        for (connector, pipeline) in &and_or.rest {
+            if self.stopping() {
+                break;
+            }
+
+            let run = match connector {
+                Connector::And => status == 0,
+                Connector::Or => status != 0,
+            };
+            if run {
+                status = self.run_pipeline(pipeline);
+            }
+        }
+
+        status
+    }
+
+    /// Starts an and-or list as a background job. A list of several
+    /// pipelines runs in a forked copy of the shell.
+    fn run_in_background(&mut self, and_or: &AndOr) -> Result<(), ShellError> {
+        if and_or.rest.is_empty() {
+            return self.execute_pipeline(&and_or.first, true).map(|_| ());
+        }
+
+        stdout().flush()?;
+
+        let mut pgid = 0;
+        let pid = self.spawn(&mut pgid, false)?;
+        if pid == 0 {
+            let status = self.execute_and_or(and_or);
+            let _ = stdout().flush();
+            process::exit(self.exit_status.unwrap_or(status));
+        }
+
+        let job = Job::new(pgid, vec![Process::new(pid)], and_or.to_string());
+        self.add_background_job(job);
+        Ok(())
+    }
+
+    fn add_background_job(&mut self, mut job: Job) {
+        job.notified = true;
+        self.last_background = Some(job.last_pid());
+
+        let pid = job.last_pid();
+        let id = self.jobs.insert(job);
+        if self.job_control {
+            eprintln!("[{}] {}", id, pid);
+        }
+    }
+
+    /// Runs a pipeline and records its status as `$?`, reporting the error
+    /// when it could not run.
+    fn run_pipeline(&mut self, pipeline: &Pipeline) -> i32 {
+        self.last_status = match self.execute_pipeline(pipeline, false) {
+            Ok(status) => status,
+            Err(err) => {
+                eprintln!("{}", err);
+                err.status()
+            }
+        };
+
+        self.last_status
+    }
+
+    fn execute_pipeline(
+        &mut self,
+        pipeline: &Pipeline,
+        background: bool,
+    ) -> Result<i32, ShellError> {
+        stdout().flush()?;
+
+        let commands = pipeline
+            .commands
+            .iter()
+            .map(|stage| self.prepare_stage(stage))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // A lone builtin or compound command runs inside the shell so that
+        // `cd` and friends can change its state.
+        if !background && let [command] = commands.as_slice() {
+            match command {
+                PreparedStage::Simple(command)
+                    if command.argv.first().is_none_or(|name| {
+                        self.functions.contains_key(name) || self.commands.contains_key(name)
+                    }) =>
+                {
+                    return self.run_builtin(command);
+                }
+                PreparedStage::Function(function) => {
+                    self.functions.insert(function.name.clone(), function.clone());
+                    return Ok(0);
+                }
+                PreparedStage::Compound(command, redirects) => {
+                    let _saved = SavedFds::save(redirects);
+                    redirect::apply(redirects)?;
+                    return self.execute_compound(command);
+                }
+                PreparedStage::Simple(_) => {}
+            }
+        }
+
+        let last = commands.len() - 1;
+        let mut pgid = 0;
+        let mut processes = Vec::new();
+        let mut stdin: Option<PipeReader> = None;
+        let mut result = Ok(());
+
+        for (i, command) in commands.iter().enumerate() {
+            let (reader, writer) = if i < last {
+                match pipe() {
+                    Ok((reader, writer)) => (Some(reader), Some(writer)),
+                    Err(e) => {
+                        result = Err(ShellError::IoError(e));
+                        break;
+                    }
+                }
+            } else {
+                (None, None)
+            };
+
+            match self.spawn(&mut pgid, !background) {
                 Ok(0) => {
-                    println!("CTRL + D exit...");
+                    drop(reader);
+                    self.run_stage(command, stdin, writer);
+                }
+                Ok(pid) => processes.push(Process::new(pid)),
+                Err(e) => {
+                    result = Err(e);
                     break;
                 }
-                Ok(_) => {
-                    input.pop();
+            }
+
+            stdin = reader;
+        }
+        drop(stdin);
+
+        if processes.is_empty() {
+            return result.map(|()| 0);
+        }
+
+        let job = Job::new(pgid, processes, pipeline.to_string());
+        if background {
+            self.add_background_job(job);
+            return result.map(|()| 0);
+        }
+
+        let status = self.run_foreground(job, false);
+        result?;
+        status
+    }
+
+    /// Forks a process of a job whose process group is `pgid`, or a new
+    /// group when it is 0, which `pgid` is then set to. Returns 0 in the
+    /// child, which gets the terminal when in the `foreground`.
+    fn spawn(&mut self, pgid: &mut libc::pid_t, foreground: bool) -> Result<libc::pid_t, ShellError> {
+        let pid = exec::fork()?;
+
+        if pid == 0 {
+            unsafe {
+                if self.job_control {
+                    let group = if *pgid == 0 { libc::getpid() } else { *pgid };
+                    libc::setpgid(0, group);
+                    if foreground {
+                        jobs::give_terminal(group);
+                    }
+                } else if !foreground {
+                    // Without job control, background jobs must not compete
+                    // with the shell for its input.
+                    if let Ok(null) = File::open("/dev/null") {
+                        let _ = exec::redirect_fd(&null, 0);
+                    }
+                }
+
+                for signal in [
+                    libc::SIGINT,
+                    libc::SIGPIPE,
+                    libc::SIGTSTP,
+                    libc::SIGTTIN,
+                    libc::SIGTTOU,
+                    libc::SIGQUIT,
+                ] {
+                    libc::signal(signal, libc::SIG_DFL);
+                }
+            }
+
+            // A subshell has no job control and no jobs of its own.
+            self.job_control = false;
+            self.jobs = Jobs::default();
+            return Ok(0);
+        }
+
+        if self.job_control {
+            if *pgid == 0 {
+                *pgid = pid;
+            }
+            unsafe { libc::setpgid(pid, *pgid) };
+        }
+
+        Ok(pid)
+    }
+
+    /// Runs a job in the foreground until it ends or stops, continuing it
+    /// first when `resume` is set. A stopped job goes to the job table.
+    fn run_foreground(&mut self, mut job: Job, resume: bool) -> Result<i32, ShellError> {
+        if self.job_control {
+            jobs::give_terminal(job.pgid);
+            if let Some(termios) = &job.termios {
+                jobs::set_terminal_modes(termios);
+            }
+        }
+
+        if resume {
+            job.kill(libc::SIGCONT)?;
+            job.set_running();
+        }
+
+        let result = jobs::wait_for(&mut job, false);
+
+        if self.job_control {
+            if job.state() == JobState::Stopped {
+                job.termios = jobs::terminal_modes();
+            }
+            jobs::give_terminal(self.pgid);
+            if let Some(termios) = &self.termios {
+                jobs::set_terminal_modes(termios);
+            }
+        }
+        result?;
+
+        if job.state() == JobState::Stopped {
+            job.notified = true;
+            let id = self.jobs.insert(job);
+            if let Some(job) = self.jobs.get(id) {
+                eprintln!();
+                eprintln!("{}", self.jobs.format(job));
+            }
+            return Ok(128 + libc::SIGTSTP);
+        }
+
+        let Some(status) = job.status() else {
+            return Ok(0);
+        };
+
+        if status.signal() == Some(libc::SIGINT) {
+            // Killed by Ctrl+C: end the `^C` line and skip the rest of the
+            // command line.
+            eprintln!();
+            signals::interrupt();
+        } else if let Some(description) = exec::describe_signal(&status) {
+            eprintln!("{}", description);
+        }
+
+        Ok(exec::status_code(&status))
+    }
+
+    pub fn jobs(&self) -> &Jobs {
+        &self.jobs
+    }
+
+    pub fn jobs_mut(&mut self) -> &mut Jobs {
+        &mut self.jobs
+    }
+
+    pub fn job_control(&self) -> bool {
+        self.job_control
+    }
+
+    /// Brings a job to the foreground, as `fg` does, and waits for it.
+    pub fn foreground(&mut self, id: usize) -> Result<i32, ShellError> {
+        let Some(job) = self.jobs.remove(id) else {
+            return Err(ShellError::Other(format!("%{}: no such job", id)));
+        };
+
+        let resume = job.state() == JobState::Stopped;
+        self.run_foreground(job, resume)
+    }
+
+    /// Continues a stopped job in the background, as `bg` does.
+    pub fn background(&mut self, id: usize) -> Result<(), ShellError> {
+        let Some(job) = self.jobs.get_mut(id) else {
+            return Err(ShellError::Other(format!("%{}: no such job", id)));
+        };
+
+        if job.state() == JobState::Stopped {
+            job.kill(libc::SIGCONT)?;
+            job.set_running();
+        }
+        job.notified = true;
+        self.jobs.make_current(id);
+        Ok(())
+    }
+
+    /// Waits for a background job to end, as `wait` does, and removes it
+    /// from the table. Returns its status.
+    pub fn wait_job(&mut self, id: usize) -> Result<i32, ShellError> {
+        let Some(mut job) = self.jobs.remove(id) else {
+            return Err(ShellError::Other(format!("%{}: no such job", id)));
+        };
+
+        let result = jobs::wait_for(&mut job, true);
+
+        // Interrupted or stopped: the job stays in the table.
+        if job.state() != JobState::Done {
+            self.jobs.insert(job);
+            result?;
+            return Ok(128 + libc::SIGTSTP);
+        }
+        result?;
+
+        Ok(job.status().map_or(0, |status| exec::status_code(&status)))
+    }
+
+    /// Reports the jobs that finished or stopped since the last prompt, and
+    /// forgets the finished ones.
+    fn notify_jobs(&mut self) {
+        self.jobs.reap();
+
+        let ids: Vec<usize> = self.jobs.iter().map(|job| job.id).collect();
+        for id in ids {
+            let Some(job) = self.jobs.get(id) else {
+                continue;
+            };
+            if job.notified {
+                continue;
+            }
+
+            match job.state() {
+                JobState::Done => {
+                    eprintln!("{}", self.jobs.format(job));
+                    self.jobs.remove(id);
+                }
+                JobState::Stopped => {
+                    eprintln!("{}", self.jobs.format(job));
+                }
+                JobState::Running => {}
+            }
 
-                    match self.execute_command(input) {
-                        Err(err) => println!("{}", err),
-                        _ => (),
+            if let Some(job) = self.jobs.get_mut(id) {
+                job.notified = true;
+            }
+        }
+    }
+
+    /// Runs an external program with `vars` as its environment, bypassing
+    /// builtins, as `env` does. Returns its exit status.
+    pub fn run_external(&mut self, argv: &[String], vars: &Variables) -> Result<i32, ShellError> {
+        let Some((name, args)) = argv.split_first() else {
+            return Ok(0);
+        };
+
+        stdout().flush()?;
+
+        let mut pgid = 0;
+        let pid = self.spawn(&mut pgid, true)?;
+        if pid == 0 {
+            exec::exec_external(name, args, vars);
+        }
+
+        let job = Job::new(pgid, vec![Process::new(pid)], argv.join(" "));
+        self.run_foreground(job, false)
+    }
+
+    /// Runs `source` in a forked copy of the shell and returns what it
+    /// wrote to stdout, for `$(...)`. Its exit status becomes `$?`.
+    pub fn capture_output(&mut self, source: &str) -> Result<String, ShellError> {
+        stdout().flush()?;
+
+        let (mut reader, writer) = pipe()?;
+
+        let pid = exec::fork()?;
+        if pid == 0 {
+            // Commands in the substitution stay in the shell's process
+            // group, and Ctrl+C ends the substitution.
+            self.job_control = false;
+            unsafe { libc::signal(libc::SIGINT, libc::SIG_DFL) };
+
+            drop(reader);
+            if let Err(e) = exec::redirect_fd(&writer, 1) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+            drop(writer);
+
+            let status = match self.execute_command(source) {
+                Ok(status) => status,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    err.status()
+                }
+            };
+            let _ = stdout().flush();
+            process::exit(self.exit_status.unwrap_or(status));
+        }
+        drop(writer);
+
+        let mut output = Vec::new();
+        let result = reader.read_to_end(&mut output);
+        drop(reader);
+
+        let status = exec::wait_pid(pid)?;
+        if status.signal() == Some(libc::SIGINT) || signals::interrupted() {
+            return Err(ShellError::Interrupted);
+        }
+        result?;
+        self.last_status = exec::status_code(&status);
+        self.substitution_status = Some(self.last_status);
+
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    /// Runs an `if`, a loop or another compound command.
+    fn execute_compound(&mut self, command: &CompoundCommand) -> Result<i32, ShellError> {
+        match command {
+            CompoundCommand::If {
+                branches,
+                otherwise,
+            } => {
+                for (condition, body) in branches {
+                    let status = self.execute_list(condition);
+                    if self.stopping() {
+                        return Ok(status);
+                    }
+                    if status == 0 {
+                        return Ok(self.execute_list(body));
+                    }
+                }
+                Ok(match otherwise {
+                    Some(otherwise) => self.execute_list(otherwise),
+                    None => 0,
+                })
+            }
+            CompoundCommand::Case { word, items } => self.execute_case(word, items),
+            CompoundCommand::Group(list) => Ok(self.execute_list(list)),
+            CompoundCommand::Arithmetic(expression) => {
+                let expression = expand_word(expression, self)?;
+                let value = evaluate_arithmetic(&expression, self)?;
+                Ok(if value != 0 { 0 } else { 1 })
+            }
+            CompoundCommand::While { .. }
+            | CompoundCommand::For { .. }
+            | CompoundCommand::ArithmeticFor { .. } => {
+                self.loop_depth += 1;
+                let result = self.execute_loop(command);
+                self.loop_depth -= 1;
+                result
+            }
+        }
+    }
+
+    /// Runs the commands of the first item of a `case` with a pattern
+    /// matching `word`, and those after it as its terminator says. Its
+    /// status is that of the last command run, 0 when none ran.
+    fn execute_case(&mut self, word: &Word, items: &[CaseItem]) -> Result<i32, ShellError> {
+        let value = expand_word(word, self)?;
+        let mut status = 0;
+        let mut fall_through = false;
+
+        for item in items {
+            if !fall_through {
+                let mut matched = false;
+                for pattern in &item.patterns {
+                    if match_pattern(&value, pattern, self)? {
+                        matched = true;
+                        break;
                     }
                 }
-                Err(error) => {
-                    println!("ERROR: {}", error)
+                if !matched {
+                    continue;
                 }
+            }
+
+            status = if item.body.is_empty() {
+                0
+            } else {
+                self.execute_list(&item.body)
             };
+            if self.stopping() {
+                break;
+            }
+
+            match item.terminator {
+                CaseTerminator::Break => break,
+                CaseTerminator::FallThrough => fall_through = true,
+                CaseTerminator::Continue => fall_through = false,
+            }
         }
+
+        Ok(status)
     }
 
-    fn execute_command(&mut self, input: String) -> Result<(), ShellError> {
-        let (cmd, args) = parser::parse_command(input)?;
+    /// Runs a loop. Its status is that of the last command of its body, or
+    /// 0 when the body did not run.
+    fn execute_loop(&mut self, command: &CompoundCommand) -> Result<i32, ShellError> {
+        let mut status = 0;
 
-        if cmd.is_empty() {
-            return Ok(());
+        match command {
+            CompoundCommand::While {
+                condition,
+                body,
+                until,
+            } => loop {
+                let condition_status = self.execute_list(condition);
+                if self.end_of_iteration() || (condition_status == 0) == *until {
+                    break;
+                }
+                status = self.execute_list(body);
+                if self.end_of_iteration() {
+                    break;
+                }
+            },
+            CompoundCommand::For { name, words, body } => {
+                let values = match words {
+                    Some(words) => expand_words(words, self)?,
+                    None => self.positional.clone(),
+                };
+                for value in values {
+                    self.vars.set(name, &value);
+                    status = self.execute_list(body);
+                    if self.end_of_iteration() {
+                        break;
+                    }
+                }
+            }
+            CompoundCommand::ArithmeticFor {
+                init,
+                condition,
+                step,
+                body,
+            } => {
+                let init = expand_word(init, self)?;
+                evaluate_arithmetic(&init, self)?;
+                loop {
+                    // An empty condition is always true.
+                    let value = expand_word(condition, self)?;
+                    if !value.trim().is_empty() && evaluate_arithmetic(&value, self)? == 0 {
+                        break;
+                    }
+                    status = self.execute_list(body);
+                    if self.end_of_iteration() {
+                        break;
+                    }
+                    let step = expand_word(step, self)?;
+                    evaluate_arithmetic(&step, self)?;
+                }
+            }
+            CompoundCommand::If { .. }
+            | CompoundCommand::Case { .. }
+            | CompoundCommand::Group(_)
+            | CompoundCommand::Arithmetic(_) => unreachable!("not a loop"),
         }
 
-        match self.commands.get(&cmd) {
-            None => Err(ShellError::CommandNotFound(cmd)),
-            Some(command) => command.execute(args),
+        if signals::interrupted() {
+            return Ok(signals::INTERRUPTED_STATUS);
+        }
+        Ok(status)
+    }
+
+    /// Whether the innermost loop must end after a pass through its
+    /// condition or its body. Takes the `break` or `continue` meant for it,
+    /// and passes on what is left for the loops around it.
+    fn end_of_iteration(&mut self) -> bool {
+        let leave = match self.flow.take() {
+            Some(Flow::Break(n)) => {
+                if n > 1 {
+                    self.flow = Some(Flow::Break(n - 1));
+                }
+                true
+            }
+            Some(Flow::Continue(n)) if n > 1 => {
+                self.flow = Some(Flow::Continue(n - 1));
+                true
+            }
+            Some(Flow::Continue(_)) | None => false,
+            // Leaves the loop and goes on up to the function.
+            Some(flow @ Flow::Return(_)) => {
+                self.flow = Some(flow);
+                true
+            }
+        };
+
+        leave || self.exit_status.is_some() || signals::interrupted()
+    }
+
+    /// Expands a stage of a pipeline: all of a simple command, only the
+    /// redirections of a compound one, whose words are expanded as it runs.
+    fn prepare_stage<'a>(&mut self, stage: &'a Stage) -> Result<PreparedStage<'a>, ShellError> {
+        match stage {
+            Stage::Simple(command) => self.prepare(command).map(PreparedStage::Simple),
+            Stage::Compound(command, redirects) => Ok(PreparedStage::Compound(
+                command,
+                self.prepare_redirects(redirects)?,
+            )),
+            Stage::Function(function) => Ok(PreparedStage::Function(function.clone())),
+        }
+    }
+
+    /// Expands the assignments, words and redirection targets of a command.
+    fn prepare(&mut self, command: &SimpleCommand) -> Result<PreparedCommand, ShellError> {
+        self.substitution_status = None;
+
+        let mut assignments = Vec::new();
+        for assignment in &command.assignments {
+            let value = expand_word(&assignment.value, self)?;
+            assignments.push((assignment.name.clone(), value));
+        }
+
+        let argv = expand_words(&command.words, self)?;
+        let redirects = self.prepare_redirects(&command.redirects)?;
+
+        Ok(PreparedCommand {
+            substitution_status: self.substitution_status.take(),
+            assignments,
+            argv,
+            redirects,
+        })
+    }
+
+    fn prepare_redirects(
+        &mut self,
+        redirects: &[Redirect],
+    ) -> Result<Vec<Redirection>, ShellError> {
+        let mut prepared = Vec::new();
+        for redirect in redirects {
+            prepared.push(Redirection {
+                fd: redirect.fd,
+                op: redirect.op,
+                target: expand_word(&redirect.target, self)?,
+            });
+        }
+        Ok(prepared)
+    }
+
+    fn run_builtin(&mut self, command: &PreparedCommand) -> Result<i32, ShellError> {
+        let _saved = SavedFds::save(&command.redirects);
+        redirect::apply(&command.redirects)?;
+
+        let Some((name, args)) = command.argv.split_first() else {
+            // Assignments alone set shell variables. Such a command reports
+            // the status of its last command substitution, as in `x=$(false)`.
+            for (name, value) in &command.assignments {
+                self.vars.set(name, value);
+            }
+            return Ok(command.substitution_status.unwrap_or(0));
+        };
+
+        let saved = self.vars.set_temporary(&command.assignments);
+        let status = if let Some(function) = self.functions.get(name).cloned() {
+            self.call_function(&function, args.to_vec())
+        } else if let Some(builtin) = self.commands.get(name).cloned() {
+            self.invoke(name, builtin, args.to_vec())
+        } else {
+            0
+        };
+        self.vars.restore(saved);
+
+        Ok(status)
+    }
+
+    /// Runs a function with `args` as its positional parameters, in a scope
+    /// of its own for `local` variables. Returns its status: that of its
+    /// last command, or the one given to `return`.
+    fn call_function(&mut self, function: &Function, args: Vec<String>) -> i32 {
+        if self.function_depth >= MAX_FUNCTION_DEPTH {
+            eprintln!(
+                "{}: maximum function nesting level exceeded ({})",
+                function.name, MAX_FUNCTION_DEPTH
+            );
+            return 1;
+        }
+
+        let positional = self.set_positional(args);
+        // `break` and `continue` do not reach the loops of the caller.
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        self.function_depth += 1;
+        self.vars.push_scope();
+
+        let result = self.prepare_redirects(&function.redirects).and_then(|redirects| {
+            let _saved = SavedFds::save(&redirects);
+            redirect::apply(&redirects)?;
+            self.execute_compound(&function.body)
+        });
+        let mut status = match result {
+            Ok(status) => status,
+            Err(err) => {
+                eprintln!("{}", err);
+                err.status()
+            }
+        };
+        if let Some(Flow::Return(returned)) = self.flow {
+            self.flow = None;
+            status = returned;
+        }
+
+        self.vars.pop_scope();
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+        self.positional = positional;
+        status
+    }
+
+    /// Calls a builtin with the process' current standard streams, reporting
+    /// its error, if any, on its own stderr. Returns the exit status.
+    fn invoke(&mut self, name: &str, builtin: Rc<dyn Command>, args: Vec<String>) -> i32 {
+        // A fresh reader on a copy of fd 0: whatever it buffers past what the
+        // command consumes must not leak into the next command's input.
+        let mut stdin: Box<dyn BufRead> = match io::stdin().as_fd().try_clone_to_owned() {
+            Ok(fd) => Box::new(BufReader::new(File::from(fd))),
+            Err(_) => Box::new(io::empty()),
+        };
+        // Likewise a writer of its own on a copy of fd 1: what it could not
+        // write goes away with it instead of staying in the process-wide
+        // stdout, to be flushed wherever fd 1 points once the redirections
+        // are undone.
+        let mut stdout = BuiltinOutput::new();
+        let mut stderr = io::stderr();
+        let stdout_is_terminal = io::stdout().is_terminal();
+
+        colored::control::set_override(stdout_is_terminal);
+
+        let mut ctx = Context {
+            stdin: &mut stdin,
+            stdout: &mut stdout,
+            stderr: &mut stderr,
+            stdin_is_terminal: io::stdin().is_terminal(),
+            stdout_is_terminal,
+            shell: self,
+        };
+
+        let result = builtin.execute(&mut ctx, args);
+        let _ = ctx.stdout.flush();
+
+        colored::control::unset_override();
+
+        if let Some(err) = stdout.error.take() {
+            drop(stdout);
+            let _ = writeln!(stderr, "{}: write error: {}", name, describe(&err));
+            return 1;
+        }
+        drop(stdout);
+
+        match result {
+            Ok(status) => {
+                // Stopped by Ctrl+C: end the `^C` line, as for a child.
+                if status == signals::INTERRUPTED_STATUS && signals::interrupted() {
+                    let _ = writeln!(stderr);
+                }
+                status
+            }
+            Err(err) => {
+                let _ = writeln!(stderr, "{}", err);
+                err.status()
+            }
+        }
+    }
+
+    /// Runs one stage of a pipeline inside a forked child and exits.
+    fn run_stage(
+        &mut self,
+        stage: &PreparedStage,
+        stdin: Option<PipeReader>,
+        stdout: Option<PipeWriter>,
+    ) -> ! {
+        if let Some(reader) = stdin
+            && let Err(e) = exec::redirect_fd(&reader, 0)
+        {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        if let Some(writer) = stdout
+            && let Err(e) = exec::redirect_fd(&writer, 1)
+        {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+
+        let command = match stage {
+            PreparedStage::Simple(command) => command,
+            // Defined in the child only, which is about to exit.
+            PreparedStage::Function(_) => process::exit(0),
+            PreparedStage::Compound(command, redirects) => {
+                if let Err(err) = redirect::apply(redirects) {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+
+                let status = match self.execute_compound(command) {
+                    Ok(status) => status,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        err.status()
+                    }
+                };
+                let _ = io::stdout().flush();
+                process::exit(self.exit_status.unwrap_or(status));
+            }
+        };
+
+        if let Err(err) = redirect::apply(&command.redirects) {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+
+        let Some((name, args)) = command.argv.split_first() else {
+            process::exit(0);
+        };
+
+        // The child exits after this command, so nothing needs restoring.
+        let _saved = self.vars.set_temporary(&command.assignments);
+
+        if let Some(function) = self.functions.get(name).cloned() {
+            let status = self.call_function(&function, args.to_vec());
+            let _ = io::stdout().flush();
+            process::exit(self.exit_status.unwrap_or(status));
+        }
+
+        let Some(builtin) = self.commands.get(name).cloned() else {
+            exec::exec_external(name, args, &self.vars);
+        };
+
+        let status = self.invoke(name, builtin, args.to_vec());
+        process::exit(status);
+    }
+}
+
+/// The stdout of a builtin: a line-buffered writer on a copy of fd 1, which
+/// keeps the first error writing to it.
+struct BuiltinOutput {
+    writer: Box<dyn Write>,
+    error: Option<io::Error>,
+}
+
+impl BuiltinOutput {
+    fn new() -> Self {
+        let writer: Box<dyn Write> = match io::stdout().as_fd().try_clone_to_owned() {
+            Ok(fd) => Box::new(LineWriter::new(File::from(fd))),
+            // fd 1 is closed: there is nowhere to write to.
+            Err(_) => Box::new(io::sink()),
+        };
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    fn keep_error<T>(&mut self, result: io::Result<T>) -> io::Result<T> {
+        if let Err(err) = &result
+            && self.error.is_none()
+        {
+            self.error = Some(match err.raw_os_error() {
+                Some(code) => io::Error::from_raw_os_error(code),
+                None => io::Error::new(err.kind(), err.to_string()),
+            });
+        }
+        result
+    }
+}
+
+impl Write for BuiltinOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.writer.write(buf);
+        self.keep_error(result)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let result = self.writer.flush();
+        self.keep_error(result)
+    }
+}
+
+/// A command ready to run: its assignments, words and redirections expanded.
+struct PreparedCommand {
+    assignments: Vec<(String, String)>,
+    /// The status of the last `$(...)` in the command, if it had one.
+    substitution_status: Option<i32>,
+    argv: Vec<String>,
+    redirects: Vec<Redirection>,
+}
+
+/// A stage of a pipeline ready to run.
+enum PreparedStage<'a> {
+    Simple(PreparedCommand),
+    /// A compound command and its expanded redirections.
+    Compound(&'a CompoundCommand, Vec<Redirection>),
+    Function(Rc<Function>),
+}
+
+/// The lines typed at the prompt, after history expansion. Continuation
+/// lines get the `PS2` prompt, `> ` by default.
+struct Prompt<'a> {
+    shell: &'a mut Shell,
+}
+
+impl<'a> Prompt<'a> {
+    fn new(shell: &'a mut Shell) -> Self {
+        Self { shell }
+    }
+}
+
+impl LineSource for Prompt<'_> {
+    fn read_line(&mut self, continuation: bool) -> Result<Option<String>, ShellError> {
+        let prompt = match continuation {
+            true => self.shell.var("PS2").unwrap_or_else(|| "> ".to_string()),
+            false => self.shell.prompt(),
+        };
+
+        let line = match self.shell.read_line(&prompt) {
+            Ok(Some(line)) => line,
+            Ok(None) => return Ok(None),
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {
+                return Err(ShellError::Interrupted);
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        match parser::expand_history(&line, self.shell.history.entries())? {
+            Some(expanded) => {
+                // Show what is about to run, as other shells do.
+                println!("{}", expanded);
+                Ok(Some(expanded))
+            }
+            None => Ok(Some(line)),
         }
     }
 }
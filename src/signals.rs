@@ -0,0 +1,43 @@
+//! SIGINT handling. The shell catches Ctrl+C instead of dying from it: the
+//! handler only raises a flag, which interrupts blocking reads and waits
+//! and which long-running builtins poll to stop early.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The status of a command stopped by Ctrl+C, as for a child killed by
+/// SIGINT.
+pub const INTERRUPTED_STATUS: i32 = 128 + libc::SIGINT;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Catches SIGINT. System calls it interrupts fail with `EINTR` instead of
+/// being restarted, so that a blocked read returns to the shell.
+pub fn install() {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = 0;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+    }
+}
+
+/// Whether Ctrl+C was pressed since the last `clear`.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Records an interruption that happened elsewhere, as when the foreground
+/// job was killed by SIGINT, so that the rest of the command line is
+/// skipped.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+pub fn clear() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
@@ -1,4 +1,4 @@
-use std::{env, path::PathBuf};
+use std::{io, path::PathBuf};
 
 use regex::Regex;
 
@@ -16,6 +16,32 @@ pub fn strip_ansi_codes(s: &str) -> String {
     re.replace_all(s, "").to_string()
 }
 
+/// Reads one line from the shell's standard input, newline included.
+///
+/// Bytes are read one at a time so that nothing past the newline is
+/// consumed: whatever follows belongs to the commands we are about to run.
+pub fn read_line(buf: &mut String) -> io::Result<usize> {
+    let mut line = Vec::new();
+    let mut byte = 0u8;
+
+    loop {
+        let n = unsafe { libc::read(0, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+        match n {
+            0 => break,
+            1 => {
+                line.push(byte);
+                if byte == b'\n' {
+                    break;
+                }
+            }
+            _ => return Err(io::Error::last_os_error()),
+        }
+    }
+
+    buf.push_str(&String::from_utf8_lossy(&line));
+    Ok(line.len())
+}
+
 // I get it from here "https://patorjk.com/software/taag"
 pub fn print_welcome() {
     let title = r#"
@@ -39,21 +65,19 @@ pub fn print_welcome() {
     println!();
 }
 
-pub fn print_cur_dir(path: PathBuf) {
+/// The prompt: the current directory, with the home directory shown as `~`.
+pub fn prompt(path: PathBuf, home_dir: &str) -> String {
     let current_path = path.to_string_lossy();
-    let home_dir = env::var("HOME").unwrap_or_else(|_| "/".to_string());
 
-    let display_path = if current_path.starts_with(&home_dir) {
-        current_path.replacen(&home_dir, "~", 1)
+    let display_path = if current_path.starts_with(home_dir) {
+        current_path.replacen(home_dir, "~", 1)
     } else {
         current_path.to_string()
     };
 
-    let prompt = format!(
+    format!(
         "{} {} ",
-        colorize(&format!("{display_path}"), Color::Blue, true),
+        colorize(&display_path, Color::Blue, true),
         colorize("➤", Color::Red, true)
-    );
-
-    print!("{}", prompt);
+    )
 }
@@ -0,0 +1,177 @@
+use std::{collections::BTreeMap, env};
+
+#[derive(Debug, Clone)]
+struct Variable {
+    value: String,
+    /// Passed on to the environment of the programs the shell starts.
+    exported: bool,
+}
+
+/// The shell's variables. Those inherited from the environment start out
+/// exported; new ones are local to the shell until `export`ed.
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    vars: BTreeMap<String, Variable>,
+    /// For each running function, its `local` variables as they were
+    /// before it made them local.
+    scopes: Vec<SavedVars>,
+}
+
+impl Variables {
+    pub fn from_env() -> Self {
+        let vars = env::vars()
+            .map(|(name, value)| {
+                let var = Variable {
+                    value,
+                    exported: true,
+                };
+                (name, var)
+            })
+            .collect();
+
+        Self {
+            vars,
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(|var| var.value.as_str())
+    }
+
+    /// Sets a variable, keeping it exported if it already was.
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self.vars.get_mut(name) {
+            Some(var) => var.value = value.to_string(),
+            None => {
+                let var = Variable {
+                    value: value.to_string(),
+                    exported: false,
+                };
+                self.vars.insert(name.to_string(), var);
+            }
+        }
+    }
+
+    /// Marks a variable as exported, setting its value when one is given.
+    /// Exporting an unset variable without a value creates it empty.
+    pub fn export(&mut self, name: &str, value: Option<&str>) {
+        let var = self
+            .vars
+            .entry(name.to_string())
+            .or_insert_with(|| Variable {
+                value: String::new(),
+                exported: true,
+            });
+        var.exported = true;
+        if let Some(value) = value {
+            var.value = value.to_string();
+        }
+    }
+
+    /// Keeps a variable but stops passing it to programs (`export -n`).
+    pub fn unexport(&mut self, name: &str) {
+        if let Some(var) = self.vars.get_mut(name) {
+            var.exported = false;
+        }
+    }
+
+    pub fn unset(&mut self, name: &str) {
+        self.vars.remove(name);
+    }
+
+    /// Exports `assignments` for the duration of one command, as in
+    /// `VAR=value cmd`. The returned value puts the variables back with
+    /// `restore`.
+    pub fn set_temporary(&mut self, assignments: &[(String, String)]) -> SavedVars {
+        let mut saved = Vec::new();
+
+        for (name, value) in assignments {
+            saved.push((name.clone(), self.vars.get(name).cloned()));
+            self.export(name, Some(value));
+        }
+
+        SavedVars(saved)
+    }
+
+    pub fn restore(&mut self, saved: SavedVars) {
+        for (name, var) in saved.0.into_iter().rev() {
+            match var {
+                Some(var) => self.vars.insert(name, var),
+                None => self.vars.remove(&name),
+            };
+        }
+    }
+
+    /// Starts the scope of a function call.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(SavedVars(Vec::new()));
+    }
+
+    /// Ends the scope of a function call, putting back the variables it
+    /// made local.
+    pub fn pop_scope(&mut self) {
+        if let Some(saved) = self.scopes.pop() {
+            self.restore(saved);
+        }
+    }
+
+    /// Makes a variable local to the current function: it gets its old
+    /// value back when the function returns. Returns false outside of a
+    /// function.
+    pub fn make_local(&mut self, name: &str) -> bool {
+        let Some(scope) = self.scopes.last_mut() else {
+            return false;
+        };
+        if !scope.0.iter().any(|(saved, _)| saved == name) {
+            scope.0.push((name.to_string(), self.vars.get(name).cloned()));
+        }
+        true
+    }
+
+    /// Every variable, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars
+            .iter()
+            .map(|(name, var)| (name.as_str(), var.value.as_str()))
+    }
+
+    /// The exported variables, sorted by name: the environment of the
+    /// programs the shell starts.
+    pub fn exported(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars
+            .iter()
+            .filter(|(_, var)| var.exported)
+            .map(|(name, var)| (name.as_str(), var.value.as_str()))
+    }
+}
+
+/// Variables as they were before `Variables::set_temporary`.
+#[must_use]
+#[derive(Debug, Clone)]
+pub struct SavedVars(Vec<(String, Option<Variable>)>);
+
+/// Whether `name` can be used as a variable name: a letter or `_`, then
+/// letters, digits or `_`.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits a `NAME=value` word into its name and value.
+pub fn split_assignment(word: &str) -> Option<(&str, &str)> {
+    let (name, value) = word.split_once('=')?;
+    is_valid_name(name).then_some((name, value))
+}
+
+/// Quotes a value so that the shell reads it back unchanged, as `set` and
+/// `export -p` print them. Values that need no quoting are left alone.
+pub fn quote(value: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-+./:,@%=".contains(c);
+    if !value.is_empty() && value.chars().all(plain) {
+        return value.to_string();
+    }
+
+    format!("'{}'", value.replace('\'', r"'\''"))
+}